@@ -7,11 +7,13 @@
 #[macro_export]
 macro_rules! default_pass_error(
     () => (
-        fn on_error(&mut self, err: &str) {
-            error!("Error: {:?}", err);
+        fn on_error(&mut self, err: <Self as Subscriber>::Error) {
+            debug!("on_error passing the error to my subscriber");
             match self.subscriber.as_mut() {
                 Some(s) =>  s.on_error(err),
-                None => {panic!("on_error called but I don't have a subscriber")}
+                None => if !self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+                    panic!("on_error called but I don't have a subscriber")
+                }
             }
         }
     )
@@ -23,7 +25,9 @@ macro_rules! default_pass_complete (
         fn on_complete(&mut self, force: bool) {
             match self.subscriber.as_mut() {
                 Some(s) => s.on_complete(force),
-                None => panic!("on_complete called but I don't have a subscriber")
+                None => if !self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+                    panic!("on_complete called but I don't have a subscriber")
+                }
             }
         }
     )
@@ -32,12 +36,63 @@ macro_rules! default_pass_complete (
 #[macro_export]
 macro_rules! default_pass_subscribe (
     () => (
-        fn on_subscribe(&mut self, index: usize) {
-            self.index = Some(index);
+        fn on_subscribe(&mut self, sub: Subscription) {
+            if let Some(s) = self.subscriber.as_mut() {
+                s.on_subscribe(sub.clone());
+            }
+            self.subscription = Some(sub);
         }
     )
 );
 
+/// Passes a value from a processor to its subscriber.
+/// If the subscription has been cancelled, or the subscriber asks to stop,
+/// the subscription is cancelled so everything upstream learns of it, and
+/// the subscriber is released, which drops the rest of the chain with it
+#[macro_export]
+macro_rules! pass_next (
+    ($this:ident, $val:expr) => ({
+        if $this.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+            $this.subscriber = None;
+            false
+        } else {
+            let more = match $this.subscriber.as_mut() {
+                Some(s) => s.on_next($val),
+                None => true
+            };
+            if !more {
+                if let Some(sub) = $this.subscription.as_ref() { sub.cancel(); }
+                $this.subscriber = None;
+            }
+            more
+        }
+    })
+);
+
+/// For a processor which swallows an item rather than passing it on.
+/// The demand it used up is handed back to the subscription, since
+/// the subscriber downstream never saw it
+#[macro_export]
+macro_rules! drop_next (
+    ($this:ident) => ({
+        if let Some(sub) = $this.subscription.as_ref() { sub.request(1); }
+        true
+    })
+);
+
+/// For a processor which has decided that its stream is over.
+/// Completes its subscriber, cancels the subscription and
+/// releases the subscriber
+#[macro_export]
+macro_rules! complete_early (
+    ($this:ident) => ({
+        if let Some(s) = $this.subscriber.as_mut() { s.on_complete(false); }
+        if let Some(sub) = $this.subscription.as_ref() { sub.cancel(); }
+        $this.subscriber = None;
+        false
+    })
+);
+
 #[macro_export]
 macro_rules! protocol_size {
     ($($name:ident = $value:expr),+) => {$(
@@ -0,0 +1,33 @@
+// Copyright (C) 2015 <Rick Richardson r@12sidedtech.com>
+//
+// This software may be modified and distributed under the terms
+// of the MIT license.  See the LICENSE file for details.
+
+//! Errors produced by the publishers which ship with this crate.
+//! These are passed through on_error unchanged, so a subscriber
+//! further down the chain can match on them
+
+use mio::MioError;
+
+/// Failures surfaced by the NetEngine for a socket.
+/// They are delivered to the subscriber of the NetStreamer
+/// reading from that socket (or from the listener which accepted it)
+#[derive(Debug, Clone)]
+pub enum NetError {
+    /// A new connection could not be accepted by a listener
+    Accept(MioError),
+    /// Reading from the socket failed
+    Read(MioError),
+    /// Writing to the socket failed
+    Write(MioError)
+}
+
+/// Signalled by a RateLimit set to error, when an item
+/// arrives and the limit leaves no room for it
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RateLimited;
+
+/// Signalled by a Timeout with no fallback,
+/// when its upstream has gone quiet for too long
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimedOut;
@@ -30,7 +30,16 @@
 //!
 //! ## Example
 //!
-//! ```rust
+//! ```ignore
+//! use rx::publisher::{IterPublisher, PublisherExt};
+//! use rx::subscriber::StdoutSubscriber;
+//! use rx::reactive::Publisher;
+//!
+//! let mut chain = IterPublisher::<_, _, ()>::new(range(0is, 20is))
+//!     .map(|i| i * 10)
+//!     .take(5);
+//! chain.subscribe(Box::new(StdoutSubscriber::new()));
+//! chain.run();
 //! ```
 #![doc(html_root_url = "http://www.rust-ci.org/rrichardson/reactive/doc/reactive/")]
 #![unstable]
@@ -65,12 +74,15 @@ extern crate lazy_static;
 #[macro_use]
 pub mod default_macros;
 pub mod subscriber;
+pub mod error;
 pub mod reactive;
 pub mod reactor;
 pub mod net_stream;
 pub mod sendable;
 pub mod mmap_allocator;
 pub mod scheduler;
+pub mod strategy;
+pub mod subject;
 #[macro_use]
 pub mod protocol;
 mod processorimpl;
@@ -101,14 +113,14 @@ fn main() {
     let (dtx, drx) = channel();
 
     let out = move |:| {
-        let sub = Box::new(StdoutSubscriber::new());
+        let sub = Box::new(StdoutSubscriber::<isize, ()>::new());
         let mut rec = Box::new(Coupler::new(drx));
         rec.subscribe(sub);
     };
 
     let gen = move |:| {
         let it = range(0is, 20is);
-        let q   = Box::new(Decoupler::new(dtx.clone()));
+        let q   = Box::new(Decoupler::<_, _, ()>::new(dtx.clone()));
         let mut map1 = Box::new(Map::new(|i : isize| {i * 10}));
         let mut map2 = Box::new(Map::new(|i : isize| {i + 2}));
         let mut iter = Box::new(IterPublisher::new(it));
@@ -127,7 +139,7 @@ fn main() {
 
     Thread::spawn(|| {
         let it = range(0is, 20is);
-        let sub = Box::new(StdoutSubscriber::new());
+        let sub = Box::new(StdoutSubscriber::<isize, ()>::new());
         let mut map1 = Box::new(Map::new(|i : isize| {i * 10}));
         let mut map2 = Box::new(Map::new(|i : isize| {i + 2}));
         let mut iter = Box::new(IterPublisher::new(it));
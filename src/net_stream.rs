@@ -10,13 +10,14 @@
 //! back through the sender provided by EngineInner::channel or via the
 //! StreamConneciton send_all function for Traversals
 
-use reactor::{Reactor, StreamBuf, Sender, ProtoMsg};
+use reactor::{Reactor, StreamBuf, Sender, ProtoMsg, NetResult};
 use mio::Token;
 use publisherimpl::Coupler;
-use reactive::{Publisher, Subscriber};
+use reactive::{Publisher, Subscriber, Subscription};
 use iobuf::{AROIobuf};
+use error::NetError;
 
-use std::sync::mpsc::{Receiver,SyncSender};
+use std::sync::mpsc::{Receiver,SyncSender,TryRecvError};
 use std::sync::Arc;
 use std::cell::RefCell;
 
@@ -26,7 +27,7 @@ type Superbox<T> = Arc<RefCell<Box<T>>>;
 #[derive(Clone)]
 pub struct NetStream<'a, U : Send> {
     pub dtx: Sender,
-    pub drx: Arc<Receiver<ProtoMsg<U>>>,
+    pub drx: Arc<Receiver<NetResult<U>>>,
     pub tok: Token,
 }
 
@@ -34,7 +35,7 @@ pub struct NetStream<'a, U : Send> {
 impl<'a, U : Send> NetStream<'a, U>
 {
     pub fn new(tok: Token,
-               drx: Receiver<ProtoMsg<U>>,
+               drx: Receiver<NetResult<U>>,
                dtx: Sender) -> NetStream<'a, U> {
         NetStream { tok: tok, drx: Arc::new(drx), dtx: dtx.clone() }
     }
@@ -42,19 +43,63 @@ impl<'a, U : Send> NetStream<'a, U>
 
 pub struct NetStreamer<'a, U : Send>
 {
-    stream: NetStream<'a, U>,
-    subscriber: Option<Box<Subscriber<Input=ProtoMsg<U>> + 'a >>
+    tok: Token,
+    dtx: Sender,
+    drx: Option<Arc<Receiver<NetResult<U>>>>,
+    pending: Option<ProtoMsg<U>>,
+    subscriber: Option<Box<Subscriber<Input=ProtoMsg<U>, Error=NetError> + 'a >>,
+    subscription: Subscription
     //subscriber: Option<Box<Subscriber<Input=<NetStreamer<'a> as Publisher<'a>>::Output> + 'a >>
 }
 
+impl<'a, U : Send> NetStreamer<'a, U>
+{
+    pub fn new(stream: NetStream<'a, U>) -> NetStreamer<'a, U> {
+        NetStreamer {
+            tok: stream.tok,
+            dtx: stream.dtx,
+            drx: Some(stream.drx),
+            pending: None,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    /// Passes on what came off the queue. A message is only passed on
+    /// if it has been asked for, otherwise it is held back until it is
+    fn deliver(&mut self, next: Result<NetResult<U>, TryRecvError>) -> bool {
+        let more = match self.subscriber.as_mut() {
+            Some(s) => match next {
+                Ok(Ok(d)) => if self.subscription.take() { s.on_next(d) } else { self.pending = Some(d); true },
+                Ok(Err(e)) => { s.on_error(e); false },
+                Err(TryRecvError::Empty) => true,
+                Err(TryRecvError::Disconnected) => { s.on_complete(false); false }
+            },
+            None => { error!("My subscriber went away"); false }
+        };
+        if !more { self.release(); }
+        more
+    }
+
+    /// Drops the subscriber and our handle on the receiving queue
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        self.drx = None;
+        self.pending = None;
+        false
+    }
+}
+
 impl<'a, U : Send> Subscriber for NetStreamer<'a, U>
 {
     type Input = StreamBuf; 
+    type Error = NetError;
     fn on_next(&mut self, StreamBuf (buf, _) : StreamBuf) -> bool {
 
         //TODO better handle queue failure, maybe put the returned buf
         //isizeo a recovery queue
-        match self.stream.dtx.send(StreamBuf(buf, self.stream.tok)) {
+        match self.dtx.send(StreamBuf(buf, self.tok)) {
             Ok(()) => true,
             Err(_) => false
         }
@@ -64,22 +109,43 @@ impl<'a, U : Send> Subscriber for NetStreamer<'a, U>
 impl<'a, U : Send> Publisher<'a> for NetStreamer<'a, U> 
 {
     type Output = ProtoMsg<U>;
+    type Error = NetError;
 
     //fn subscribe(&mut self, s: Box<Subscriber<Input=<Self as Publisher<'a>>::Output > + 'a>) {
-    fn subscribe(&mut self, s: Box<Subscriber<Input=ProtoMsg<U>> + 'a>) {
+    fn subscribe(&mut self, s: Box<Subscriber<Input=ProtoMsg<U>, Error=NetError> + 'a>) {
         //let t: Box<Subscriber<Input=<Self as Publisher<'a>>::Output> + 'a> = s;
+        self.subscription = Subscription::new(0);
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
     }
 
+    // Only blocks on the queue once there is demand, without it the queue
+    // is still looked at, so that an error or its end can be passed on
     fn next (&mut self) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) => match self.stream.drx.recv() {
-                Ok(d) => s.on_next(d),
-                Err(..) => { s.on_complete(false); false }
-            },
-            None => { error!("My subscriber went away"); false }
-        }
+        if self.subscription.is_cancelled() { return self.release() }
+        let received = match self.drx.as_ref() {
+            Some(drx) if self.pending.is_none() && self.subscription.demand() > 0 => Some(drx.recv()),
+            _ => None
+        };
+        let more = match received {
+            Some(Ok(r)) => self.deliver(Ok(r)),
+            Some(Err(..)) => self.deliver(Err(TryRecvError::Disconnected)),
+            None => self.try_next()
+        };
+        if more && self.subscription.demand() == 0 { self.subscription.wait(); }
+        more
+    }
+
+    // Does not block. A message which arrives before it is asked for is
+    // held back, so an error or the end of the queue behind it can be noticed
+    fn try_next(&mut self) -> bool {
+        if self.subscription.is_cancelled() { return self.release() }
+        let next = match (self.pending.take(), self.drx.as_ref()) {
+            (Some(d), _) => Ok(Ok(d)),
+            (None, Some(drx)) => drx.try_recv(),
+            (None, None) => Err(TryRecvError::Disconnected)
+        };
+        self.deliver(next)
     }
 }
 
@@ -97,8 +163,10 @@ use mio::Token;
 use iobuf::{Iobuf, RWIobuf, AROIobuf};
 use std::time::Duration;
 use protocol::Protocol;
-use publisher::{Repeat, Coupler};
+use publisher::{Repeat, Interval, PublisherExt};
+use net_stream::NetStreamer;
 use processor::{Map, Take, DoDebug};
+use std::sync::mpsc::channel;
 use subscriber::{Decoupler, Collect};
 use reactive::{Publisher, Subscriber};
 
@@ -141,7 +209,7 @@ use reactive::{Publisher, Subscriber};
     fn oneway_test() {
 
         let mut ne = NetEngine::<U64Protocol>::new();
-        let srv_rx = ne.listen("127.0.0.1", 10000).unwrap();
+        let srv = ne.listen("127.0.0.1", 10000).unwrap();
         let cl = { ne.connect("127.0.0.1", 10000).unwrap().clone() };
 
         ne.timeout(Duration::milliseconds(500), Box::new(|&: el : &mut Reactor| { el.shutdown(); true}));
@@ -150,7 +218,7 @@ use reactive::{Publisher, Subscriber};
         let dtx = cl.dtx.clone();
 
         Thread::spawn(move|| {
-            let mut rep = Box::new(Repeat::new(5u64));
+            let mut rep = Box::new(Repeat::<_, ()>::new(5u64));
             let mut map1 = Box::new(Map::new(|x| isize_to_strbuf(&x)));
             let mut map2 = Box::new(Map::new(move | StreamBuf (buf, _) | StreamBuf (buf, tok)));
             let mut trc  = Box::new(DoDebug::new());
@@ -167,7 +235,7 @@ use reactive::{Publisher, Subscriber};
 
             let mut v = Box::new(Vec::<u64>::new());
             {
-                let mut recv = Box::new(Coupler::new(srv_rx));
+                let mut recv = Box::new(NetStreamer::new(srv));
                 let mut take = Box::new(Take::new(5));
                 let mut map3 = Box::new(Map::new(| ProtoMsg (x, _) | x ));
                 let mut coll = Box::new(Collect::new(&mut v));
@@ -183,6 +251,20 @@ use reactive::{Publisher, Subscriber};
 
         ne.run();
     }
+    #[test]
+    fn drive_polls_until_the_publisher_is_done() {
+        let mut ne = NetEngine::<U64Protocol>::new();
+        let (tx, rx) = channel();
+        let mut ticks = Interval::<(), _>::new(Duration::milliseconds(1)).take(3);
+        ticks.subscribe(Box::new(Decoupler::new(tx)));
+        // a publisher which is done is dropped, and the sender along with it
+        ne.drive(Duration::milliseconds(10), ticks);
+        ne.timeout(Duration::milliseconds(500), Box::new(|&: el : &mut Reactor| { el.shutdown(); true}));
+        ne.run();
+        let v: Vec<u64> = rx.iter().collect();
+        assert_eq!(v, vec![0, 1, 2]);
+    }
+
     /*
     #[test]
     fn roundtrip_test() {
@@ -5,41 +5,56 @@
 //
 
 use std::fmt::Debug;
-use reactive::{Publisher, Subscriber};
+use std::iter::IntoIterator;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::mem;
+use std::vec;
+use std::time::Duration;
+use std::error::FromError;
+use std::num::Float;
+use std::collections::{RingBuf, HashMap, HashSet};
+use std::hash::Hash;
+use reactive::{Publisher, Subscriber, Subscription, Notification};
+use publisherimpl::IterPublisher;
 use sendable::Sendable;
+use strategy::{OutputStrategy, Outlet, LockStep};
+use scheduler::{Clock, RealClock, Tick, idle, idle_on, clamped_ns};
+use error::RateLimited;
 
-pub struct DoDebug<'a, I> where I : Debug {
-    subscriber: Option<Box<Subscriber<Input=I> + 'a>>,
-    index: Option<usize>
+pub struct DoDebug<'a, I, E> where I : Debug {
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>
 }
 
-impl<'a, I> DoDebug<'a, I> where I : Debug {
+impl<'a, I, E> DoDebug<'a, I, E> where I : Debug {
 
-    pub fn new() -> DoDebug<'a, I>
+    pub fn new() -> DoDebug<'a, I, E>
     {
         DoDebug {
             subscriber: None,
-            index: None
+            subscription: None
         }
     }
 }
 
-impl<'a, I> Publisher<'a> for DoDebug<'a, I> where I : Debug {
+impl<'a, I, E> Publisher<'a> for DoDebug<'a, I, E> where I : Debug {
     type Output = I;
-    fn subscribe(&mut self, s: Box<Subscriber<Input=I> + 'a>) {
-        let t: Box<Subscriber<Input=I>+'a> = s;
+    type Error = E;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let t: Box<Subscriber<Input=I, Error=E>+'a> = s;
         self.subscriber = Some(t);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
     }
 }
 
-impl<'a, I> Subscriber for DoDebug<'a, I> where I : Debug {
+impl<'a, I, E> Subscriber for DoDebug<'a, I, E> where I : Debug, E : Debug {
     type Input = I;
+    type Error = E;
     fn on_next(&mut self, t: I) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) => { println!("{:?}", t); s.on_next(t) },
-            None => {true}
-        }
+        pass_next!(self, { println!("{:?}", t); t })
     }
 
     default_pass_subscribe!();
@@ -47,47 +62,48 @@ impl<'a, I> Subscriber for DoDebug<'a, I> where I : Debug {
     default_pass_error!();
 }
 
-pub struct DebugWhile<'a, I, F> where I : Debug, F : Fn(&I) -> bool {
+pub struct DebugWhile<'a, I, E, F> where I : Debug, F : Fn(&I) -> bool {
     fun: F,
-    subscriber: Option<Box<Subscriber<Input=I> + 'a>>,
-    index: Option<usize>
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>
 }
 
 
-impl<'a, I, F> DebugWhile<'a, I, F> where I : 'a + Debug, F : Fn(&I) -> bool{
+impl<'a, I, E, F> DebugWhile<'a, I, E, F> where I : 'a + Debug, F : Fn(&I) -> bool{
 
-    pub fn new( f: F ) -> DebugWhile<'a, I, F>
+    pub fn new( f: F ) -> DebugWhile<'a, I, E, F>
     {
         DebugWhile {
             fun: f,
             subscriber: None,
-            index: None
+            subscription: None
         }
     }
 }
 
-impl<'a, I, F> Publisher<'a> for DebugWhile<'a, I, F> where I : Debug, F : Fn(&I) -> bool  {
+impl<'a, I, E, F> Publisher<'a> for DebugWhile<'a, I, E, F> where I : Debug, F : Fn(&I) -> bool  {
     type Output = I;
-    fn subscribe(&mut self, s: Box<Subscriber<Input=I> + 'a>) {
-        let s: Box<Subscriber<Input=I>+'a> = s;
+    type Error = E;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
     }
 }
 
-impl<'a, I, F> Subscriber for DebugWhile<'a, I, F> where I : Debug, F : Fn(&I) -> bool {
+impl<'a, I, E, F> Subscriber for DebugWhile<'a, I, E, F> where I : Debug, F : Fn(&I) -> bool, E : Debug {
     type Input = I;
+    type Error = E;
 
     default_pass_subscribe!();
     default_pass_complete!();
     default_pass_error!();
 
     fn on_next(&mut self, t: I) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) => { if (self.fun)(&t) { println!("{:?}", t) }
-                         s.on_next(t) }
-            None => {true}
-        }
+        pass_next!(self, { if (self.fun)(&t) { println!("{:?}", t) }
+                           t })
     }
 }
 
@@ -95,42 +111,43 @@ impl<'a, I, F> Subscriber for DebugWhile<'a, I, F> where I : Debug, F : Fn(&I) -
 /// Applies a () function at each iteration to the supplied value
 /// which does not effect the result passed to the subscriber
 
-pub struct Do<'a, I, F> where F : Fn(&I) -> () {
+pub struct Do<'a, I, E, F> where F : Fn(&I) -> () {
     fun: F,
-    subscriber: Option<Box<Subscriber<Input=I> + 'a>>,
-    index: Option<usize>
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>
 }
 
 
-impl<'a, I, F> Do<'a, I, F> where F : Fn(&I) -> () {
+impl<'a, I, E, F> Do<'a, I, E, F> where F : Fn(&I) -> () {
 
-    pub fn new(f: F) -> Do<'a, I, F>
+    pub fn new(f: F) -> Do<'a, I, E, F>
     {
         Do {
             fun: f,
             subscriber: None,
-            index: None
+            subscription: None
         }
     }
 }
 
-impl<'a, I, F> Publisher<'a> for Do<'a, I, F> where F : Fn(&I) -> () {
+impl<'a, I, E, F> Publisher<'a> for Do<'a, I, E, F> where F : Fn(&I) -> () {
     type Output = I;
-    fn subscribe(&mut self, s: Box<Subscriber<Input=I> + 'a>) {
-        let s: Box<Subscriber<Input=I>+'a> = s;
+    type Error = E;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
     }
 }
 
-impl<'a, I, F> Subscriber for Do<'a, I, F> where F : Fn(&I) -> () {
+impl<'a, I, E, F> Subscriber for Do<'a, I, E, F> where F : Fn(&I) -> (), E : Debug {
     type Input = I;
+    type Error = E;
 
     fn on_next(&mut self, t: I) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) => { (self.fun)(&t); s.on_next(t) }
-            None => {true}
-        }
+        pass_next!(self, { (self.fun)(&t); t })
     }
 
     default_pass_subscribe!();
@@ -144,42 +161,43 @@ impl<'a, I, F> Subscriber for Do<'a, I, F> where F : Fn(&I) -> () {
 ///
 ///
 
-pub struct Map<'a, I, O, F> where F : Fn(I) -> O {
+pub struct Map<'a, I, O, E, F> where F : Fn(I) -> O {
     fun: F,
-    subscriber: Option<Box<Subscriber<Input=O> + 'a>>,
-    index: Option<usize>
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Option<Subscription>
 }
 
 
-impl<'a, I, O, F> Map<'a, I, O, F> where F : Fn(I) -> O {
+impl<'a, I, O, E, F> Map<'a, I, O, E, F> where F : Fn(I) -> O {
 
-    pub fn new(f: F) -> Map<'a, I, O, F>
+    pub fn new(f: F) -> Map<'a, I, O, E, F>
     {
         Map {
             fun: f,
             subscriber: None,
-            index: None
+            subscription: None
         }
     }
 }
 
-impl<'a, I, O, F> Publisher<'a> for Map<'a, I, O, F> where F : Fn(I) -> O {
+impl<'a, I, O, E, F> Publisher<'a> for Map<'a, I, O, E, F> where F : Fn(I) -> O {
     type Output = O;
-    fn subscribe(&mut self, s: Box<Subscriber<Input=O> + 'a>) {
-        let s: Box<Subscriber<Input=O>+'a> = s;
+    type Error = E;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
     }
 }
 
-impl<'a, I, O, F> Subscriber for Map<'a, I, O, F> where F : Fn(I) -> O {
+impl<'a, I, O, E, F> Subscriber for Map<'a, I, O, E, F> where F : Fn(I) -> O, E : Debug {
     type Input = I;
+    type Error = E;
 
     fn on_next(&mut self, t: I) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) => s.on_next((self.fun)(t)),
-            None => {true}
-        }
+        pass_next!(self, (self.fun)(t))
     }
 
     default_pass_subscribe!();
@@ -188,49 +206,50 @@ impl<'a, I, O, F> Subscriber for Map<'a, I, O, F> where F : Fn(I) -> O {
 }
 
 
-pub struct MapVal1<'a, 'c, I, V, O, F> where O : 'c, V : Clone, F : Fn(I,&V) -> O {
+pub struct MapVal1<'a, 'c, I, V, O, E, F> where O : 'c, V : Clone, F : Fn(I,&V) -> O {
     fun: F,
-    subscriber: Option<Box<Subscriber<Input=O> + 'a>>,
-    index: Option<usize>,
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
     val: V
 }
 
 
-impl<'a, 'c, I, V, O, F> MapVal1<'a, 'c, I, V, O, F> where O : 'c, V : Clone, F : Fn(I,&V) -> O {
+impl<'a, 'c, I, V, O, E, F> MapVal1<'a, 'c, I, V, O, E, F> where O : 'c, V : Clone, F : Fn(I,&V) -> O {
 
-    pub fn new( v : V, f: F ) -> MapVal1<'a, 'c, I, V, O, F>
+    pub fn new( v : V, f: F ) -> MapVal1<'a, 'c, I, V, O, E, F>
     {
         MapVal1 {
             fun: f,
             subscriber: None,
-            index: None,
+            subscription: None,
             val: v.clone()
         }
     }
 }
 
-impl<'a, 'c, I, V, O, F> Publisher<'a> for MapVal1<'a, 'c, I, V, O, F> where O : 'c, V : Clone, F : Fn(I,&V) -> O {
+impl<'a, 'c, I, V, O, E, F> Publisher<'a> for MapVal1<'a, 'c, I, V, O, E, F> where O : 'c, V : Clone, F : Fn(I,&V) -> O {
     type Output = O;
+    type Error = E;
 
-    fn subscribe(&mut self, s: Box<Subscriber<Input=O> + 'a>) {
-        let s: Box<Subscriber<Input=O>+'a> = s;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
      }
 }
 
-impl<'a, 'c, I, V, O, F> Subscriber for MapVal1<'a, 'c, I, V, O, F> where O : 'c, V : Clone, F : Fn(I,&V) -> O {
+impl<'a, 'c, I, V, O, E, F> Subscriber for MapVal1<'a, 'c, I, V, O, E, F> where O : 'c, V : Clone, F : Fn(I,&V) -> O, E : Debug {
     type Input = I;
+    type Error = E;
 
     default_pass_subscribe!();
     default_pass_complete!();
     default_pass_error!();
 
     fn on_next(&mut self, t: I) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) =>  s.on_next((self.fun)(t, &self.val)),
-            None => {true}
-        }
+        pass_next!(self, (self.fun)(t, &self.val))
     }
 
 }
@@ -240,39 +259,43 @@ impl<'a, 'c, I, V, O, F> Subscriber for MapVal1<'a, 'c, I, V, O, F> where O : 'c
 // Reduce
 //
 
-pub struct Reduce<'a, 'c, I, V, O, F> where O : 'c, V : Copy, F : Fn(V,I) -> (V, O) {
+pub struct Reduce<'a, 'c, I, V, O, E, F> where O : 'c, V : Copy, F : Fn(V,I) -> (V, O) {
     fun: F,
-    subscriber: Option<Box<Subscriber<Input=O> + 'a>>,
-    index: Option<usize>,
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
     state: V
 }
 
 
-impl<'a, 'c, I, V, O, F> Reduce<'a, 'c, I, V, O, F> where O : 'c, V : Copy, F : Fn(V,I) -> (V, O) {
+impl<'a, 'c, I, V, O, E, F> Reduce<'a, 'c, I, V, O, E, F> where O : 'c, V : Copy, F : Fn(V,I) -> (V, O) {
 
-    pub fn new( initial : V, f: F ) -> Reduce<'a, 'c, I, V, O, F>
+    pub fn new( initial : V, f: F ) -> Reduce<'a, 'c, I, V, O, E, F>
     {
         Reduce {
             fun: f,
             subscriber: None,
-            index: None,
+            subscription: None,
             state: initial
         }
     }
 }
 
-impl<'a, 'c, I, V, O, F> Publisher<'a> for Reduce<'a, 'c, I, V, O, F> where O : 'c, V : Copy, F : Fn(V,I) -> (V, O) {
+impl<'a, 'c, I, V, O, E, F> Publisher<'a> for Reduce<'a, 'c, I, V, O, E, F> where O : 'c, V : Copy, F : Fn(V,I) -> (V, O) {
     type Output = O;
+    type Error = E;
 
-    fn subscribe(&mut self, s: Box<Subscriber<Input=O> + 'a>) {
-        let s: Box<Subscriber<Input=O>+'a> = s;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
      }
 }
 
-impl<'a, 'c, I, V, O, F> Subscriber for Reduce<'a, 'c, I, V, O, F> where O : 'c, V : Copy, F : Fn(V,I) -> (V, O) {
+impl<'a, 'c, I, V, O, E, F> Subscriber for Reduce<'a, 'c, I, V, O, E, F> where O : 'c, V : Copy, F : Fn(V,I) -> (V, O), E : Debug {
     type Input = I;
+    type Error = E;
 
     default_pass_subscribe!();
     default_pass_complete!();
@@ -281,10 +304,7 @@ impl<'a, 'c, I, V, O, F> Subscriber for Reduce<'a, 'c, I, V, O, F> where O : 'c,
     fn on_next(&mut self, t: I) -> bool {
         let (newstate, outval) = (self.fun)(self.state, t);
         self.state = newstate;
-        match self.subscriber.as_mut() {
-            Some(s) =>  { s.on_next(outval) }
-            None => {true}
-        }
+        pass_next!(self, outval)
     }
 }
 
@@ -293,49 +313,50 @@ impl<'a, 'c, I, V, O, F> Subscriber for Reduce<'a, 'c, I, V, O, F> where O : 'c,
 //
 //
 
-pub struct Enumerate<'a, I> {
-    subscriber: Option<Box<Subscriber<Input=(I, u64)> + 'a>>,
-    index: Option<usize>,
+pub struct Enumerate<'a, I, E> {
+    subscriber: Option<Box<Subscriber<Input=(I, u64), Error=E> + 'a>>,
+    subscription: Option<Subscription>,
     count: u64
 }
 
 
-impl<'a, I> Enumerate<'a, I> {
+impl<'a, I, E> Enumerate<'a, I, E> {
 
-    pub fn new() -> Enumerate<'a, I>
+    pub fn new() -> Enumerate<'a, I, E>
     {
         Enumerate {
             subscriber: None,
-            index: None,
+            subscription: None,
             count: 0
         }
     }
 }
 
-impl<'a, I> Publisher<'a> for Enumerate<'a, I> {
+impl<'a, I, E> Publisher<'a> for Enumerate<'a, I, E> {
     type Output = (I, u64);
+    type Error = E;
 
-    fn subscribe(&mut self, s: Box<Subscriber<Input=(I, u64)> + 'a>) {
-        let s: Box<Subscriber<Input=(I, u64)>+'a> = s;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=(I, u64), Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=(I, u64), Error=E>+'a> = s;
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
      }
 }
 
-impl<'a, I> Subscriber for Enumerate<'a, I> {
+impl<'a, I, E> Subscriber for Enumerate<'a, I, E> where E : Debug {
     type Input = I;
+    type Error = E;
 
     default_pass_subscribe!();
     default_pass_complete!();
     default_pass_error!();
 
     fn on_next(&mut self, t: I) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) =>  { let c = self.count;
-                          self.count += 1;
-                          s.on_next( (t, c)  ) }
-            None => {true}
-        }
+        pass_next!(self, { let c = self.count;
+                           self.count += 1;
+                           (t, c) })
     }
 }
 
@@ -343,58 +364,61 @@ impl<'a, I> Subscriber for Enumerate<'a, I> {
 /// Sends the value off to a supplied Sendable queue,
 /// then passes the data to its subscriber
 ///
-pub struct Tee<'a, Q, I>
+pub struct Tee<'a, Q, I, E>
 where I : Send + Clone,
 Q : Sendable
 {
 
-    subscriber: Option<Box<Subscriber<Input=I> + 'a>>,
-    index: Option<usize>,
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
     data_tx: Q,
 }
 
-impl<'a, Q, I> Tee<'a, Q, I>
+impl<'a, Q, I, E> Tee<'a, Q, I, E>
 where I : Send + Clone,
 Q : Sendable
 {
 
-    pub fn new(tx: Q) -> Tee<'a, Q,I> {
+    pub fn new(tx: Q) -> Tee<'a, Q, I, E> {
         Tee {
-            index: None,
+            subscription: None,
             subscriber: None,
             data_tx: tx,
         }
     }
 }
 
-impl<'a, Q, I> Publisher<'a> for Tee<'a, Q, I>
+impl<'a, Q, I, E> Publisher<'a> for Tee<'a, Q, I, E>
 where I : Send + Clone,
 Q : Sendable<Item=I> {
 
     type Output = I;
 
-    fn subscribe(&mut self, s: Box<Subscriber<Input=I> + 'a>) {
-        let s: Box<Subscriber<Input=I>+'a> = s;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
      }
 }
 
-impl<'a, Q, I> Subscriber for Tee<'a, Q, I>
-where I : Send + Clone,
+impl<'a, Q, I, E> Subscriber for Tee<'a, Q, I, E>
+where I : Send + Clone, E : Debug,
 Q : Sendable<Item=I> {
 
     type Input = I;
 
+    type Error = E;
+
     default_pass_subscribe!();
     default_pass_complete!();
     default_pass_error!();
 
     fn on_next(&mut self, t: I) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) =>  { self.data_tx.send(t.clone()); s.on_next(t) }
-            None => {true}
-        }
+        pass_next!(self, { self.data_tx.send(t.clone()); t })
     }
 }
 
@@ -402,69 +426,137 @@ Q : Sendable<Item=I> {
 
 /// Unzip
 /// takes tuples of identical items as input
-/// unpacks them into their own message
+/// unpacks them into their own message.
+/// The upstream uses up one unit of demand on a tuple, which pays for
+/// the first item, the second is only sent on once there is demand for it.
+/// Hung off of a publisher with Ticked, as unzip does, it is ticked as the
+/// publisher is polled, so a held item goes out once it is asked for, ahead
+/// of the next tuple, and completion waits until it has gone
 ///
-pub struct Unzip<'a, I>
+pub struct Unzip<'a, I, E>
 {
-    subscriber: Option<Box<Subscriber<Input=I> + 'a>>,
-    index: Option<usize>,
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    held: RingBuf<I>,
+    completed: Option<bool>
 }
 
-impl<'a, I> Unzip<'a, I> {
-    pub fn new() -> Unzip<'a, I> {
+impl<'a, I, E> Unzip<'a, I, E> {
+    pub fn new() -> Unzip<'a, I, E> {
         Unzip {
-            index: None,
+            subscription: None,
             subscriber: None,
+            held: RingBuf::new(),
+            completed: None
+        }
+    }
+
+    /// Sends on whatever is held back while there is demand for it,
+    /// the first item for free if the upstream has already paid for it.
+    /// Completes the subscriber once nothing is left and the stream is over.
+    /// Returns false once the stream has ended
+    fn send_held(&mut self, paid: bool) -> bool {
+        let mut paid = paid;
+        while !self.held.is_empty() {
+            if !paid && !self.subscription.as_ref().map_or(false, |s| s.take()) {
+                return true
+            }
+            paid = false;
+            let t = self.held.pop_front().unwrap();
+            if !pass_next!(self, t) {
+                self.held.clear();
+                return false
+            }
+        }
+        match self.completed {
+            Some(force) => {
+                if let Some(mut s) = self.subscriber.take() { s.on_complete(force); }
+                false
+            },
+            None => self.subscriber.is_some()
         }
     }
 }
 
-impl<'a, I> Publisher<'a> for Unzip<'a, I>
+impl<'a, I, E> Publisher<'a> for Unzip<'a, I, E>
 {
     type Output = I;
+    type Error = E;
 
-    fn subscribe(&mut self, s: Box<Subscriber<Input=I> + 'a>) {
-        let s: Box<Subscriber<Input=I>+'a> = s;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
      }
 }
 
-impl<'a, I> Subscriber for Unzip<'a, I>
+impl<'a, I, E> Subscriber for Unzip<'a, I, E> where E : Debug
 {
     type Input = (I,I);
+    type Error = E;
     default_pass_subscribe!();
-    default_pass_complete!();
-    default_pass_error!();
 
     fn on_next(&mut self, t: (I,I)) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) =>  {
-                s.on_next(t.0) | s.on_next(t.1)
-            }
-            None => {true}
+        // anything still held goes out first, the tuple joins the back
+        self.held.push_back(t.0);
+        self.held.push_back(t.1);
+        self.send_held(true)
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.held.clear();
+        match self.subscriber.take() {
+            Some(mut s) => s.on_error(err),
+            None => debug!("on_error called but I don't have a subscriber")
+        }
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        self.completed = Some(force);
+        self.send_held(false);
+    }
+}
+
+impl<'a, I, E> Tick for Unzip<'a, I, E> where E : Debug {
+
+    /// Sends on what is held back, as far as there is demand for it
+    fn tick(&mut self) -> bool {
+        if self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+            self.subscriber = None;
+            self.held.clear();
         }
+        if self.subscriber.is_none() {
+            return false
+        }
+        self.send_held(false)
+    }
+
+    fn idle(&self) {
+        idle_on(self.subscription.as_ref())
     }
 }
 
 
 /// Take
-/// takes tuples of identical items as input
-/// unpacks them into their own message
+/// passes on the first max items, then completes its subscriber
+/// and cancels its subscription. With a max of 0 that happens as
+/// soon as it is subscribed, without waiting for an item
 ///
-pub struct Take<'a, O>
+pub struct Take<'a, O, E>
 {
-    subscriber: Option<Box<Subscriber<Input=O> + 'a>>,
-    index: Option<usize>,
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
     count: usize,
     max: usize,
     notified: bool
 }
 
-impl<'a, O> Take<'a, O> {
-    pub fn new(max: usize) -> Take<'a, O> {
+impl<'a, O, E> Take<'a, O, E> {
+    pub fn new(max: usize) -> Take<'a, O, E> {
         Take {
-            index: None,
+            subscription: None,
             subscriber: None,
             count: 0,
             max: max,
@@ -473,34 +565,2957 @@ impl<'a, O> Take<'a, O> {
     }
 }
 
-impl<'a, O> Publisher<'a> for Take<'a, O>
+impl<'a, O, E> Take<'a, O, E> {
+
+    /// With a max of 0 the stream is over before it starts, so once both
+    /// ends are wired up the subscriber is completed and upstream cancelled
+    fn take_nothing(&mut self) {
+        if self.max == 0 && !self.notified && self.subscriber.is_some() && self.subscription.is_some() {
+            self.notified = true;
+            complete_early!(self);
+        }
+    }
+}
+
+impl<'a, O, E> Publisher<'a> for Take<'a, O, E>
 {
     type Output = O;
+    type Error = E;
 
-    fn subscribe(&mut self, s: Box<Subscriber<Input=O> + 'a>) {
-        let s: Box<Subscriber<Input=O>+'a> = s;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+        self.take_nothing();
      }
 }
 
-impl<'a, O> Subscriber for Take<'a, O>
+impl<'a, O, E> Subscriber for Take<'a, O, E> where E : Debug
 {
     type Input = O;
-    default_pass_subscribe!();
+    type Error = E;
     default_pass_complete!();
     default_pass_error!();
 
+    fn on_subscribe(&mut self, sub: Subscription) {
+        if let Some(s) = self.subscriber.as_mut() {
+            s.on_subscribe(sub.clone());
+        }
+        self.subscription = Some(sub);
+        self.take_nothing();
+    }
+
     fn on_next(&mut self, t: O) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) =>  {
-                self.count += 1;
-                if self.count > self.max { s.on_complete(false); self.notified = true; false }
-                else { s.on_next(t) }
-            },
-            None => {true}
+        if self.notified { return false }
+        self.count += 1;
+        let more = pass_next!(self, t);
+        if more && self.count >= self.max {
+            // we have all we came for, so complete downstream and
+            // cancel upstream rather than waiting for one more item
+            self.notified = true;
+            complete_early!(self)
+        } else {
+            more
+        }
+    }
+}
+
+
+
+/// Filter
+/// passes on only those items for which the supplied function returns true
+///
+pub struct Filter<'a, I, E, F> where F : Fn(&I) -> bool {
+    fun: F,
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>
+}
+
+impl<'a, I, E, F> Filter<'a, I, E, F> where F : Fn(&I) -> bool {
+
+    pub fn new(f: F) -> Filter<'a, I, E, F>
+    {
+        Filter {
+            fun: f,
+            subscriber: None,
+            subscription: None
+        }
+    }
+}
+
+impl<'a, I, E, F> Publisher<'a> for Filter<'a, I, E, F> where F : Fn(&I) -> bool {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E, F> Subscriber for Filter<'a, I, E, F> where F : Fn(&I) -> bool, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        if (self.fun)(&t) { pass_next!(self, t) }
+        else { drop_next!(self) }
+    }
+}
+
+/// FilterMap
+/// applies a function to each item, passing on the contents of
+/// every Some and dropping every None
+///
+pub struct FilterMap<'a, I, O, E, F> where F : Fn(I) -> Option<O> {
+    fun: F,
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Option<Subscription>
+}
+
+impl<'a, I, O, E, F> FilterMap<'a, I, O, E, F> where F : Fn(I) -> Option<O> {
+
+    pub fn new(f: F) -> FilterMap<'a, I, O, E, F>
+    {
+        FilterMap {
+            fun: f,
+            subscriber: None,
+            subscription: None
+        }
+    }
+}
+
+impl<'a, I, O, E, F> Publisher<'a> for FilterMap<'a, I, O, E, F> where F : Fn(I) -> Option<O> {
+    type Output = O;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, O, E, F> Subscriber for FilterMap<'a, I, O, E, F> where F : Fn(I) -> Option<O>, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        match (self.fun)(t) {
+            Some(o) => pass_next!(self, o),
+            None => drop_next!(self)
+        }
+    }
+}
+
+// The key of an item is, unless told otherwise, a copy of the item
+fn clone_key<I>(t: &I) -> I where I : Clone {
+    t.clone()
+}
+
+/// Distinct
+/// passes on only those items whose key has not been seen before.
+/// The key is the item itself, or whatever the key function makes of it.
+/// Unbounded, every key ever seen is remembered, bounded, only the
+/// most recent capacity keys are, so an older one may be let through again
+///
+pub struct Distinct<'a, I, K, E, F> where K : Hash + Eq + Clone, F : Fn(&I) -> K {
+    key_fn: F,
+    seen: HashSet<K>,
+    order: RingBuf<K>,
+    capacity: Option<usize>,
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>
+}
+
+impl<'a, I, E> Distinct<'a, I, I, E, fn(&I) -> I> where I : Hash + Eq + Clone {
+
+    pub fn new() -> Distinct<'a, I, I, E, fn(&I) -> I> {
+        Distinct::configured(clone_key::<I> as fn(&I) -> I, None)
+    }
+
+    pub fn bounded(capacity: usize) -> Distinct<'a, I, I, E, fn(&I) -> I> {
+        Distinct::configured(clone_key::<I> as fn(&I) -> I, Some(capacity))
+    }
+}
+
+impl<'a, I, K, E, F> Distinct<'a, I, K, E, F> where K : Hash + Eq + Clone, F : Fn(&I) -> K {
+
+    pub fn by(key_fn: F) -> Distinct<'a, I, K, E, F> {
+        Distinct::configured(key_fn, None)
+    }
+
+    pub fn bounded_by(key_fn: F, capacity: usize) -> Distinct<'a, I, K, E, F> {
+        Distinct::configured(key_fn, Some(capacity))
+    }
+
+    fn configured(key_fn: F, capacity: Option<usize>) -> Distinct<'a, I, K, E, F> {
+        Distinct {
+            key_fn: key_fn,
+            seen: HashSet::new(),
+            order: RingBuf::new(),
+            capacity: capacity,
+            subscriber: None,
+            subscription: None
+        }
+    }
+}
+
+impl<'a, I, K, E, F> Publisher<'a> for Distinct<'a, I, K, E, F> where K : Hash + Eq + Clone, F : Fn(&I) -> K {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, K, E, F> Subscriber for Distinct<'a, I, K, E, F> where K : Hash + Eq + Clone, F : Fn(&I) -> K, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        let key = (self.key_fn)(&t);
+        if self.seen.contains(&key) {
+            return drop_next!(self)
+        }
+        if let Some(cap) = self.capacity {
+            if cap == 0 {
+                return pass_next!(self, t)
+            }
+            if self.order.len() == cap {
+                if let Some(old) = self.order.pop_front() {
+                    self.seen.remove(&old);
+                }
+            }
+            self.order.push_back(key.clone());
+        }
+        self.seen.insert(key);
+        pass_next!(self, t)
+    }
+}
+
+/// DistinctUntilChanged
+/// drops each item whose key is the same as the one before it,
+/// the key being the item itself, or whatever the key function makes of it
+///
+pub struct DistinctUntilChanged<'a, I, K, E, F> where K : PartialEq, F : Fn(&I) -> K {
+    key_fn: F,
+    last: Option<K>,
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>
+}
+
+impl<'a, I, E> DistinctUntilChanged<'a, I, I, E, fn(&I) -> I> where I : PartialEq + Clone {
+
+    pub fn new() -> DistinctUntilChanged<'a, I, I, E, fn(&I) -> I> {
+        DistinctUntilChanged::by(clone_key::<I> as fn(&I) -> I)
+    }
+}
+
+impl<'a, I, K, E, F> DistinctUntilChanged<'a, I, K, E, F> where K : PartialEq, F : Fn(&I) -> K {
+
+    pub fn by(key_fn: F) -> DistinctUntilChanged<'a, I, K, E, F> {
+        DistinctUntilChanged {
+            key_fn: key_fn,
+            last: None,
+            subscriber: None,
+            subscription: None
+        }
+    }
+}
+
+impl<'a, I, K, E, F> Publisher<'a> for DistinctUntilChanged<'a, I, K, E, F> where K : PartialEq, F : Fn(&I) -> K {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, K, E, F> Subscriber for DistinctUntilChanged<'a, I, K, E, F> where K : PartialEq, F : Fn(&I) -> K, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        let key = (self.key_fn)(&t);
+        if self.last.as_ref() == Some(&key) {
+            return drop_next!(self)
+        }
+        self.last = Some(key);
+        pass_next!(self, t)
+    }
+}
+
+/// Materialize
+/// passes on every signal as a Notification, so errors and completion
+/// arrive as ordinary items, followed by completion itself.
+/// Send them through a Decoupler and Coupler, or over the network,
+/// and Dematerialize on the other side, and the receiver learns how the
+/// stream ended, which otherwise never crosses a Sendable queue.
+/// The Error or Complete is an item like any other, so it is held back
+/// until it is asked for. Hung off of a publisher with Ticked, as
+/// materialize does, it is sent on as soon as it is
+///
+pub struct Materialize<'a, I, E> {
+    subscriber: Option<Box<Subscriber<Input=Notification<I, E>, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    ending: Option<(Notification<I, E>, bool)>
+}
+
+impl<'a, I, E> Materialize<'a, I, E> {
+
+    pub fn new() -> Materialize<'a, I, E> {
+        Materialize {
+            subscriber: None,
+            subscription: None,
+            ending: None
+        }
+    }
+
+    /// Sends on the Error or Complete once it has been asked for,
+    /// then completes the subscriber. Returns false once it has
+    fn send_ending(&mut self) -> bool {
+        if self.ending.is_none() || !self.subscription.as_ref().map_or(false, |s| s.take()) {
+            return self.subscriber.is_some()
+        }
+        let (n, force) = self.ending.take().unwrap();
+        if pass_next!(self, n) {
+            if let Some(mut s) = self.subscriber.take() { s.on_complete(force); }
+        }
+        false
+    }
+}
+
+impl<'a, I, E> Publisher<'a> for Materialize<'a, I, E> {
+    type Output = Notification<I, E>;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=Notification<I, E>, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=Notification<I, E>, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E> Subscriber for Materialize<'a, I, E> where E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        pass_next!(self, Notification::Next(t))
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.ending = Some((Notification::Error(err), false));
+        self.send_ending();
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        self.ending = Some((Notification::Complete, force));
+        self.send_ending();
+    }
+}
+
+impl<'a, I, E> Tick for Materialize<'a, I, E> where E : Debug {
+
+    /// Sends on the Error or Complete, once it has been asked for
+    fn tick(&mut self) -> bool {
+        if self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+            self.subscriber = None;
+            self.ending = None;
+        }
+        if self.subscriber.is_none() {
+            return false
+        }
+        self.send_ending()
+    }
+
+    fn idle(&self) {
+        idle_on(self.subscription.as_ref())
+    }
+}
+
+/// Dematerialize
+/// turns Notifications back into the signals they stand for, the first
+/// Error or Complete ends the stream, and anything after it is ignored
+///
+pub struct Dematerialize<'a, I, E> {
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>
+}
+
+impl<'a, I, E> Dematerialize<'a, I, E> {
+
+    pub fn new() -> Dematerialize<'a, I, E> {
+        Dematerialize {
+            subscriber: None,
+            subscription: None
+        }
+    }
+
+    /// Ends the stream early, with whatever the notification said
+    fn finish(&mut self, err: Option<E>) -> bool {
+        if let Some(sub) = self.subscription.as_ref() { sub.cancel(); }
+        if let Some(mut s) = self.subscriber.take() {
+            match err {
+                Some(e) => s.on_error(e),
+                None => s.on_complete(false)
+            }
         }
+        false
     }
 }
 
+impl<'a, I, E> Publisher<'a> for Dematerialize<'a, I, E> {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
 
+impl<'a, I, E> Subscriber for Dematerialize<'a, I, E> where E : Debug {
+    type Input = Notification<I, E>;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, n: Notification<I, E>) -> bool {
+        match n {
+            Notification::Next(t) => pass_next!(self, t),
+            Notification::Error(e) => self.finish(Some(e)),
+            Notification::Complete => self.finish(None)
+        }
+    }
+}
+
+/// Skip
+/// drops the first n items, then passes on everything after
+///
+pub struct Skip<'a, I, E> {
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    remaining: usize
+}
+
+impl<'a, I, E> Skip<'a, I, E> {
+    pub fn new(n: usize) -> Skip<'a, I, E> {
+        Skip {
+            subscriber: None,
+            subscription: None,
+            remaining: n
+        }
+    }
+}
+
+impl<'a, I, E> Publisher<'a> for Skip<'a, I, E> {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E> Subscriber for Skip<'a, I, E> where E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            drop_next!(self)
+        } else {
+            pass_next!(self, t)
+        }
+    }
+}
+
+/// SkipWhile
+/// drops items until the supplied function first returns false,
+/// from then on every item is passed on
+///
+pub struct SkipWhile<'a, I, E, F> where F : Fn(&I) -> bool {
+    fun: F,
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    skipping: bool
+}
+
+impl<'a, I, E, F> SkipWhile<'a, I, E, F> where F : Fn(&I) -> bool {
+
+    pub fn new(f: F) -> SkipWhile<'a, I, E, F>
+    {
+        SkipWhile {
+            fun: f,
+            subscriber: None,
+            subscription: None,
+            skipping: true
+        }
+    }
+}
+
+impl<'a, I, E, F> Publisher<'a> for SkipWhile<'a, I, E, F> where F : Fn(&I) -> bool {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E, F> Subscriber for SkipWhile<'a, I, E, F> where F : Fn(&I) -> bool, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        if self.skipping && (self.fun)(&t) {
+            return drop_next!(self)
+        }
+        self.skipping = false;
+        pass_next!(self, t)
+    }
+}
+
+/// TakeWhile
+/// passes on items until the supplied function first returns false,
+/// at which point the item is dropped, the subscriber is completed
+/// and the subscription is cancelled
+///
+pub struct TakeWhile<'a, I, E, F> where F : Fn(&I) -> bool {
+    fun: F,
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    notified: bool
+}
+
+impl<'a, I, E, F> TakeWhile<'a, I, E, F> where F : Fn(&I) -> bool {
+
+    pub fn new(f: F) -> TakeWhile<'a, I, E, F>
+    {
+        TakeWhile {
+            fun: f,
+            subscriber: None,
+            subscription: None,
+            notified: false
+        }
+    }
+}
+
+impl<'a, I, E, F> Publisher<'a> for TakeWhile<'a, I, E, F> where F : Fn(&I) -> bool {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E, F> Subscriber for TakeWhile<'a, I, E, F> where F : Fn(&I) -> bool, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        if self.notified { return false }
+        if (self.fun)(&t) {
+            pass_next!(self, t)
+        } else {
+            self.notified = true;
+            complete_early!(self)
+        }
+    }
+}
+
+/// TakeUntil
+/// passes on items up to and including the first one for which
+/// the supplied function returns true, then completes the subscriber
+/// and cancels the subscription
+///
+pub struct TakeUntil<'a, I, E, F> where F : Fn(&I) -> bool {
+    fun: F,
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    notified: bool
+}
+
+impl<'a, I, E, F> TakeUntil<'a, I, E, F> where F : Fn(&I) -> bool {
+
+    pub fn new(f: F) -> TakeUntil<'a, I, E, F>
+    {
+        TakeUntil {
+            fun: f,
+            subscriber: None,
+            subscription: None,
+            notified: false
+        }
+    }
+}
+
+impl<'a, I, E, F> Publisher<'a> for TakeUntil<'a, I, E, F> where F : Fn(&I) -> bool {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E, F> Subscriber for TakeUntil<'a, I, E, F> where F : Fn(&I) -> bool, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        if self.notified { return false }
+        let last = (self.fun)(&t);
+        let more = pass_next!(self, t);
+        if more && last {
+            self.notified = true;
+            complete_early!(self)
+        } else {
+            more
+        }
+    }
+}
+
+/// FlatMap
+/// applies a function which returns something iterable to each item,
+/// and passes on every element of the result in turn.
+/// Elements the subscriber has not asked for yet are queued, and sent
+/// on as more is asked for, ahead of anything the next item expands to.
+/// Hung off of a publisher with Ticked, as flat_map does, it is ticked as
+/// the publisher is polled, so the queue is sent on as more is asked for
+/// even once the upstream is quiet or done. Completion waits until the
+/// queue has been sent on
+///
+pub struct FlatMap<'a, I, O, U, E, F> where U : IntoIterator<Item=O>, F : Fn(I) -> U {
+    fun: F,
+    queue: RingBuf<O>,
+    completed: Option<bool>,
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Option<Subscription>
+}
+
+impl<'a, I, O, U, E, F> FlatMap<'a, I, O, U, E, F> where U : IntoIterator<Item=O>, F : Fn(I) -> U {
+
+    pub fn new(f: F) -> FlatMap<'a, I, O, U, E, F>
+    {
+        FlatMap {
+            fun: f,
+            queue: RingBuf::new(),
+            completed: None,
+            subscriber: None,
+            subscription: None
+        }
+    }
+
+    /// Sends on the queue while there is demand for it, the first element
+    /// for free if the upstream has already paid for it. Completes the
+    /// subscriber once the queue is empty and the stream is over.
+    /// Returns false once the stream has ended
+    fn send_queued(&mut self, paid: bool) -> bool {
+        let mut paid = paid;
+        while !self.queue.is_empty() {
+            if !paid && !self.subscription.as_ref().map_or(false, |s| s.take()) {
+                return true
+            }
+            paid = false;
+            let o = self.queue.pop_front().unwrap();
+            if !pass_next!(self, o) {
+                self.queue.clear();
+                return false
+            }
+        }
+        if paid {
+            // the item expanded to nothing
+            drop_next!(self);
+        }
+        match self.completed {
+            Some(force) => {
+                if let Some(mut s) = self.subscriber.take() { s.on_complete(force); }
+                false
+            },
+            None => self.subscriber.is_some()
+        }
+    }
+}
+
+impl<'a, I, O, U, E, F> Publisher<'a> for FlatMap<'a, I, O, U, E, F> where U : IntoIterator<Item=O>, F : Fn(I) -> U {
+    type Output = O;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, O, U, E, F> Subscriber for FlatMap<'a, I, O, U, E, F> where U : IntoIterator<Item=O>, F : Fn(I) -> U, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        self.queue.extend((self.fun)(t).into_iter());
+        // the upstream has already used up a unit of demand on this item,
+        // any further element needs a unit of its own
+        self.send_queued(true)
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.queue.clear();
+        match self.subscriber.take() {
+            Some(mut s) => s.on_error(err),
+            None => debug!("on_error called but I don't have a subscriber")
+        }
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        self.completed = Some(force);
+        self.send_queued(false);
+    }
+}
+
+impl<'a, I, O, U, E, F> Tick for FlatMap<'a, I, O, U, E, F> where U : IntoIterator<Item=O>, F : Fn(I) -> U, E : Debug {
+
+    /// Sends on the queue, as far as there is demand for it
+    fn tick(&mut self) -> bool {
+        if self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+            self.subscriber = None;
+            self.queue.clear();
+        }
+        if self.subscriber.is_none() {
+            return false
+        }
+        self.send_queued(false)
+    }
+
+    fn idle(&self) {
+        idle_on(self.subscription.as_ref())
+    }
+}
+
+/// RelayState
+/// What became of the publisher feeding a Relay
+pub struct RelayState<E> {
+    completed: bool,
+    stopped: bool,
+    error: Option<E>
+}
+
+impl<E> RelayState<E> {
+    pub fn new() -> RelayState<E> {
+        RelayState {
+            completed: false,
+            stopped: false,
+            error: None
+        }
+    }
+
+    /// The publisher has completed
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+
+    /// The shared subscriber asked for no more items
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Nothing more will come through the relay
+    pub fn is_finished(&self) -> bool {
+        self.completed || self.stopped || self.error.is_some()
+    }
+
+    pub fn take_error(&mut self) -> Option<E> {
+        self.error.take()
+    }
+}
+
+/// Relay
+/// Stands in for a subscriber which is shared by a succession of publishers,
+/// such as the inner publishers of ConcatMap. Items are passed straight
+/// through, while completion and errors are only recorded in the RelayState,
+/// since the shared subscriber's stream is not over just because one of
+/// its publishers is. Whoever owns the state decides what to do about them
+pub struct Relay<'a, O, E> {
+    subscriber: Rc<RefCell<Box<Subscriber<Input=O, Error=E> + 'a>>>,
+    state: Rc<RefCell<RelayState<E>>>,
+    demand: Option<Subscription>
+}
+
+impl<'a, O, E> Relay<'a, O, E> {
+    pub fn new(s: Rc<RefCell<Box<Subscriber<Input=O, Error=E> + 'a>>>,
+               state: Rc<RefCell<RelayState<E>>>) -> Relay<'a, O, E> {
+        Relay {
+            subscriber: s,
+            state: state,
+            demand: None
+        }
+    }
+
+    /// A Relay which uses up a unit of the demand on sub for each item,
+    /// for when the shared subscriber was handed sub by the owner of the state
+    pub fn counted(s: Rc<RefCell<Box<Subscriber<Input=O, Error=E> + 'a>>>,
+                   state: Rc<RefCell<RelayState<E>>>,
+                   sub: Subscription) -> Relay<'a, O, E> {
+        Relay {
+            subscriber: s,
+            state: state,
+            demand: Some(sub)
+        }
+    }
+}
+
+impl<'a, O, E> Subscriber for Relay<'a, O, E> where E : Debug {
+    type Input = O;
+    type Error = E;
+
+    fn on_next(&mut self, t: O) -> bool {
+        if let Some(sub) = self.demand.as_ref() { sub.take(); }
+        let more = self.subscriber.borrow_mut().on_next(t);
+        if !more {
+            self.state.borrow_mut().stopped = true;
+        }
+        more
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.state.borrow_mut().error = Some(err);
+    }
+
+    fn on_complete(&mut self, _: bool) {
+        self.state.borrow_mut().completed = true;
+    }
+}
+
+/// ConcatMap
+/// applies a function which returns a Publisher to each item,
+/// then runs that publisher to completion, passing everything it
+/// produces on to the subscriber, before accepting the next item.
+/// The inner publisher is only pulled on while the subscriber has demand,
+/// once it runs out the rest waits until more is asked for.
+/// Hung off of a publisher with Ticked, as concat_map does, it is ticked as
+/// the publisher is polled, so the inner publishers are pulled on as more is
+/// asked for even once the upstream is quiet or done. Completion waits until
+/// the last of them is done.
+/// An error from an inner publisher ends the whole stream
+///
+pub struct ConcatMap<'a, I, P, E, F> where P : Publisher<'a, Error=E>, F : Fn(I) -> P, E : 'a {
+    fun: F,
+    current: Option<(P, Rc<RefCell<RelayState<E>>>)>,
+    pending: RingBuf<I>,
+    completed: Option<bool>,
+    subscriber: Option<Rc<RefCell<Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=E> + 'a>>>>,
+    subscription: Option<Subscription>
+}
+
+impl<'a, I, P, E, F> ConcatMap<'a, I, P, E, F> where P : Publisher<'a, Error=E>, F : Fn(I) -> P, E : 'a {
+
+    pub fn new(f: F) -> ConcatMap<'a, I, P, E, F>
+    {
+        ConcatMap {
+            fun: f,
+            current: None,
+            pending: RingBuf::new(),
+            completed: None,
+            subscriber: None,
+            subscription: None
+        }
+    }
+}
+
+impl<'a, I, P, E, F> Publisher<'a> for ConcatMap<'a, I, P, E, F> where P : Publisher<'a, Error=E>, F : Fn(I) -> P, E : 'a {
+    type Output = <P as Publisher<'a>>::Output;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=E> + 'a>) {
+        let mut s: Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=E>+'a> = s;
+        if let Some(sub) = self.subscription.clone() {
+            s.on_subscribe(sub);
+        }
+        self.subscriber = Some(Rc::new(RefCell::new(s)));
+    }
+}
+
+impl<'a, I, P, E, F> Subscriber for ConcatMap<'a, I, P, E, F> where P : Publisher<'a, Error=E>, F : Fn(I) -> P, E : Debug + 'a {
+    type Input = I;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        if let Some(s) = self.subscriber.as_ref() {
+            s.borrow_mut().on_subscribe(sub.clone());
+        }
+        self.subscription = Some(sub);
+    }
+
+    fn on_next(&mut self, t: I) -> bool {
+        if self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+            self.release();
+            return false
+        }
+        if self.subscriber.is_none() {
+            return true
+        }
+        // the outer item itself is not passed on, only what it expands to,
+        // so the unit of demand the upstream used up on it is handed back
+        drop_next!(self);
+        self.pending.push_back(t);
+        self.pump()
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.current = None;
+        self.pending.clear();
+        match self.subscriber.take() {
+            Some(s) => s.borrow_mut().on_error(err),
+            None => debug!("on_error called but I don't have a subscriber")
+        }
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        // whatever is left is only run through as it is asked for,
+        // the subscriber is completed once it all has been
+        self.completed = Some(force);
+        self.pump();
+    }
+}
+
+impl<'a, I, P, E, F> Tick for ConcatMap<'a, I, P, E, F> where P : Publisher<'a, Error=E>, F : Fn(I) -> P, E : Debug + 'a {
+
+    /// Pulls on the inner publishers, as far as there is demand
+    fn tick(&mut self) -> bool {
+        if self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+            self.release();
+        }
+        self.pump()
+    }
+
+    fn idle(&self) {
+        idle_on(self.subscription.as_ref())
+    }
+}
+
+impl<'a, I, P, E, F> ConcatMap<'a, I, P, E, F> where P : Publisher<'a, Error=E>, F : Fn(I) -> P, E : Debug + 'a {
+
+    /// Cancels upstream and drops the subscriber, along with any inner publisher
+    fn release(&mut self) {
+        if let Some(sub) = self.subscription.as_ref() { sub.cancel(); }
+        self.subscriber = None;
+        self.current = None;
+        self.pending.clear();
+    }
+
+    /// Pulls on the inner publishers in turn while there is demand.
+    /// Completes the subscriber once they are all done and the stream is over.
+    /// Returns false if the stream has ended
+    fn pump(&mut self) -> bool {
+        let (shared, sub) = match (self.subscriber.as_ref(), self.subscription.as_ref()) {
+            (Some(s), Some(sub)) => (s.clone(), sub.clone()),
+            _ => return false
+        };
+        loop {
+            if self.current.is_none() {
+                let t = match self.pending.pop_front() {
+                    Some(t) => t,
+                    None => match self.completed {
+                        Some(force) => {
+                            self.subscriber = None;
+                            shared.borrow_mut().on_complete(force);
+                            return false
+                        },
+                        None => return true
+                    }
+                };
+                let state = Rc::new(RefCell::new(RelayState::new()));
+                let mut inner = (self.fun)(t);
+                inner.subscribe(Box::new(Relay::counted(shared.clone(), state.clone(), sub.clone())));
+                self.current = Some((inner, state));
+            }
+            let (err, stopped) = {
+                let &mut (ref mut inner, ref state) = self.current.as_mut().unwrap();
+                loop {
+                    if state.borrow().is_finished() { break }
+                    if sub.demand() == 0 { return true }
+                    if !inner.next() { break }
+                }
+                let err = state.borrow_mut().take_error();
+                let stopped = state.borrow().is_stopped();
+                (err, stopped)
+            };
+            self.current = None;
+            if let Some(err) = err {
+                shared.borrow_mut().on_error(err);
+                self.release();
+                return false
+            }
+            if stopped {
+                self.release();
+                return false
+            }
+        }
+    }
+}
+
+/// Scan
+/// keeps a piece of state which the supplied function may change as
+/// each item arrives. Whatever the function returns in a Some is passed on,
+/// a None passes nothing. Unlike Reduce the state need not be Copy
+///
+pub struct Scan<'a, I, V, O, E, F> where F : FnMut(&mut V, I) -> Option<O> {
+    fun: F,
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    state: V
+}
+
+impl<'a, I, V, O, E, F> Scan<'a, I, V, O, E, F> where F : FnMut(&mut V, I) -> Option<O> {
+
+    pub fn new(initial: V, f: F) -> Scan<'a, I, V, O, E, F>
+    {
+        Scan {
+            fun: f,
+            subscriber: None,
+            subscription: None,
+            state: initial
+        }
+    }
+}
+
+impl<'a, I, V, O, E, F> Publisher<'a> for Scan<'a, I, V, O, E, F> where F : FnMut(&mut V, I) -> Option<O> {
+    type Output = O;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, V, O, E, F> Subscriber for Scan<'a, I, V, O, E, F> where F : FnMut(&mut V, I) -> Option<O>, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        match (self.fun)(&mut self.state, t) {
+            Some(o) => pass_next!(self, o),
+            None => drop_next!(self)
+        }
+    }
+}
+
+/// Fold
+/// accumulates every item into a piece of state with the supplied function,
+/// passing on nothing until the stream completes, at which point the final
+/// state is passed on, followed by the completion
+///
+pub struct Fold<'a, I, V, E, F> where F : FnMut(&mut V, I) {
+    fun: F,
+    subscriber: Option<Box<Subscriber<Input=V, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    state: Option<V>
+}
+
+impl<'a, I, V, E, F> Fold<'a, I, V, E, F> where F : FnMut(&mut V, I) {
+
+    pub fn new(initial: V, f: F) -> Fold<'a, I, V, E, F>
+    {
+        Fold {
+            fun: f,
+            subscriber: None,
+            subscription: None,
+            state: Some(initial)
+        }
+    }
+}
+
+impl<'a, I, V, E, F> Publisher<'a> for Fold<'a, I, V, E, F> where F : FnMut(&mut V, I) {
+    type Output = V;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=V, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=V, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, V, E, F> Subscriber for Fold<'a, I, V, E, F> where F : FnMut(&mut V, I), E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        match self.state.as_mut() {
+            Some(v) => (self.fun)(v, t),
+            None => return false
+        }
+        drop_next!(self)
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        if let Some(v) = self.state.take() {
+            pass_next!(self, v);
+        }
+        match self.subscriber.as_mut() {
+            Some(s) => s.on_complete(force),
+            None => debug!("on_complete called but I don't have a subscriber")
+        }
+    }
+}
+
+/// Buffer
+/// gathers items into batches. A batch is passed on once it holds max_count
+/// items or once max_time has passed since its first item arrived, whichever
+/// comes first. A batch which has run out of time is passed on when it is
+/// ticked, or else ahead of the next item to arrive, which starts a new one.
+/// Hung off of a publisher with Ticked, as buffer and buffer_time do, it is
+/// ticked as the publisher is polled, so a quiet upstream cannot hold up a
+/// batch. Any partial batch is passed on once the stream completes and
+/// there is demand for it, the subscriber is completed after it.
+/// A negative max_time counts as none, so every batch is already due.
+/// Time is read from a RealClock, unless another clock is given with with_clock
+///
+pub struct Buffer<'a, I, E, C = RealClock> where C : Clock {
+    subscriber: Option<Box<Subscriber<Input=Vec<I>, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    buf: Vec<I>,
+    max_count: Option<usize>,
+    max_time_ns: Option<u64>,
+    started_ns: u64,
+    completed: Option<bool>,
+    clock: C
+}
+
+impl<'a, I, E> Buffer<'a, I, E, RealClock> {
+
+    /// Batches of max_count items, or whatever arrived within max_time.
+    /// Panics if max_count is 0
+    pub fn new(max_count: usize, max_time: Duration) -> Buffer<'a, I, E> {
+        Buffer::configured(Some(max_count), Some(max_time))
+    }
+
+    /// Batches of exactly max_count items, except perhaps the last.
+    /// Panics if max_count is 0
+    pub fn with_count(max_count: usize) -> Buffer<'a, I, E> {
+        Buffer::configured(Some(max_count), None)
+    }
+
+    /// Batches of whatever arrived within max_time
+    pub fn with_time(max_time: Duration) -> Buffer<'a, I, E> {
+        Buffer::configured(None, Some(max_time))
+    }
+
+    fn configured(max_count: Option<usize>, max_time: Option<Duration>) -> Buffer<'a, I, E> {
+        if max_count == Some(0) {
+            panic!("a Buffer with a max_count of 0 would never hold an item, it must be at least 1")
+        }
+        Buffer {
+            subscriber: None,
+            subscription: None,
+            buf: Vec::with_capacity(max_count.unwrap_or(0)),
+            max_count: max_count,
+            max_time_ns: max_time.map(clamped_ns),
+            started_ns: 0,
+            completed: None,
+            clock: RealClock
+        }
+    }
+}
+
+impl<'a, I, E, C> Buffer<'a, I, E, C> where C : Clock {
+
+    /// The same Buffer, reading the time from another clock
+    pub fn with_clock<C2>(self, clock: C2) -> Buffer<'a, I, E, C2> where C2 : Clock {
+        Buffer {
+            subscriber: self.subscriber,
+            subscription: self.subscription,
+            buf: self.buf,
+            max_count: self.max_count,
+            max_time_ns: self.max_time_ns,
+            started_ns: self.started_ns,
+            completed: self.completed,
+            clock: clock
+        }
+    }
+
+    fn take_batch(&mut self) -> Vec<I> {
+        let cap = self.max_count.unwrap_or(0);
+        mem::replace(&mut self.buf, Vec::with_capacity(cap))
+    }
+
+    /// The batch has been open for max_time
+    fn is_expired(&self, now: u64) -> bool {
+        match self.max_time_ns {
+            Some(d) => !self.buf.is_empty() && now - self.started_ns >= d,
+            None => false
+        }
+    }
+}
+
+impl<'a, I, E, C> Publisher<'a> for Buffer<'a, I, E, C> where C : Clock {
+    type Output = Vec<I>;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=Vec<I>, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=Vec<I>, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E, C> Subscriber for Buffer<'a, I, E, C> where C : Clock, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        let now = self.clock.now_ns();
+        // the upstream used up a unit of demand on this item,
+        // which pays for whichever batch goes out first
+        let mut paid = true;
+        if self.is_expired(now) {
+            let batch = self.take_batch();
+            if !pass_next!(self, batch) {
+                return false
+            }
+            paid = false;
+        }
+        if self.buf.is_empty() {
+            self.started_ns = now;
+        }
+        self.buf.push(t);
+
+        let full = match self.max_count {
+            Some(c) => self.buf.len() >= c,
+            None => false
+        };
+        if full && (paid || self.subscription.as_ref().map_or(false, |s| s.take())) {
+            let batch = self.take_batch();
+            pass_next!(self, batch)
+        } else if paid {
+            drop_next!(self)
+        } else {
+            true
+        }
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.buf.clear();
+        match self.subscriber.take() {
+            Some(mut s) => s.on_error(err),
+            None => debug!("on_error called but I don't have a subscriber")
+        }
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        if self.subscriber.is_none() {
+            debug!("on_complete called but I don't have a subscriber");
+        }
+        self.completed = Some(force);
+        self.finish();
+    }
+}
+
+impl<'a, I, E, C> Buffer<'a, I, E, C> where C : Clock, E : Debug {
+
+    /// Once the stream is over, passes on the partial batch when there is
+    /// demand for it, then completes the subscriber.
+    /// Returns false once the subscriber has been completed
+    fn finish(&mut self) -> bool {
+        let force = match self.completed {
+            Some(force) => force,
+            None => return true
+        };
+        if !self.buf.is_empty() {
+            if !self.subscription.as_ref().map_or(false, |s| s.take()) {
+                return true
+            }
+            let batch = self.take_batch();
+            if !pass_next!(self, batch) {
+                return false
+            }
+        }
+        if let Some(mut s) = self.subscriber.take() {
+            s.on_complete(force);
+        }
+        false
+    }
+}
+
+impl<'a, I, E, C> Tick for Buffer<'a, I, E, C> where C : Clock, E : Debug {
+
+    /// Passes on the batch if it has run out of time, and there is demand for it.
+    /// Once the stream is over, passes on what is left as it is asked for
+    fn tick(&mut self) -> bool {
+        if self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+            self.subscriber = None;
+        }
+        if self.subscriber.is_none() {
+            return false
+        }
+        if self.completed.is_some() {
+            return self.finish()
+        }
+        let now = self.clock.now_ns();
+        if self.is_expired(now) && self.subscription.as_ref().map_or(false, |s| s.take()) {
+            let batch = self.take_batch();
+            return pass_next!(self, batch)
+        }
+        true
+    }
+
+    fn idle(&self) {
+        if self.completed.is_some() {
+            return idle_on(self.subscription.as_ref())
+        }
+        let due = match self.max_time_ns {
+            Some(d) if !self.buf.is_empty() => Some(self.started_ns + d),
+            _ => None
+        };
+        idle(&self.clock, due)
+    }
+}
+
+/// ThrottleFirst
+/// passes on the first item of each window and drops the rest.
+/// A window opens with the first item to arrive while none is open.
+/// Time is read from a RealClock, unless another clock is given with with_clock
+///
+pub struct ThrottleFirst<'a, I, E, C = RealClock> where C : Clock {
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    window_ns: u64,
+    closes_ns: Option<u64>,
+    clock: C
+}
+
+impl<'a, I, E> ThrottleFirst<'a, I, E, RealClock> {
+
+    pub fn new(window: Duration) -> ThrottleFirst<'a, I, E, RealClock> {
+        ThrottleFirst::with_clock(window, RealClock)
+    }
+}
+
+impl<'a, I, E, C> ThrottleFirst<'a, I, E, C> where C : Clock {
+
+    pub fn with_clock(window: Duration, clock: C) -> ThrottleFirst<'a, I, E, C> {
+        ThrottleFirst {
+            subscriber: None,
+            subscription: None,
+            window_ns: clamped_ns(window),
+            closes_ns: None,
+            clock: clock
+        }
+    }
+}
+
+impl<'a, I, E, C> Publisher<'a> for ThrottleFirst<'a, I, E, C> where C : Clock {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E, C> Subscriber for ThrottleFirst<'a, I, E, C> where C : Clock, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        let now = self.clock.now_ns();
+        if self.closes_ns.map_or(false, |c| now < c) {
+            return drop_next!(self)
+        }
+        self.closes_ns = Some(now + self.window_ns);
+        pass_next!(self, t)
+    }
+}
+
+// What a RateLimit does with an item the limit leaves no room for
+enum Excess<E> {
+    Block,
+    Drop,
+    Error(fn(RateLimited) -> E)
+}
+
+/// RateLimit
+/// holds the stream to per_second items a second, with bursts of up to
+/// burst items, as a token bucket. What happens to an item arriving when
+/// the bucket is empty depends on how it was created: blocking sleeps until
+/// there is room, which holds up the publisher's next loop as fixed_loop
+/// would, dropping drops the item, and erroring ends the stream with a
+/// RateLimited error. per_second must be more than 0, a burst of 0 is
+/// taken as 1. Time is read from a RealClock, unless another clock is
+/// given with with_clock
+///
+pub struct RateLimit<'a, I, E, C = RealClock> where C : Clock {
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    excess: Excess<E>,
+    per_ns: f64,
+    burst: f64,
+    tokens: f64,
+    last_ns: Option<u64>,
+    clock: C
+}
+
+impl<'a, I, E> RateLimit<'a, I, E, RealClock> {
+
+    /// Sleeps until there is room for the item
+    pub fn blocking(per_second: u64, burst: usize) -> RateLimit<'a, I, E, RealClock> {
+        RateLimit::configured(per_second, burst, Excess::Block)
+    }
+
+    /// Drops the item when there is no room for it
+    pub fn dropping(per_second: u64, burst: usize) -> RateLimit<'a, I, E, RealClock> {
+        RateLimit::configured(per_second, burst, Excess::Drop)
+    }
+
+    /// Ends the stream with an error when there is no room for the item
+    pub fn erroring(per_second: u64, burst: usize) -> RateLimit<'a, I, E, RealClock>
+    where E : FromError<RateLimited>
+    {
+        RateLimit::configured(per_second, burst, Excess::Error(FromError::from_error))
+    }
+
+    fn configured(per_second: u64, burst: usize, excess: Excess<E>) -> RateLimit<'a, I, E, RealClock> {
+        if per_second == 0 {
+            panic!("a RateLimit of 0 items a second would let nothing through after its first burst")
+        }
+        let burst = if burst == 0 { 1.0 } else { burst as f64 };
+        RateLimit {
+            subscriber: None,
+            subscription: None,
+            excess: excess,
+            per_ns: per_second as f64 / 1_000_000_000.0,
+            burst: burst,
+            tokens: burst,
+            last_ns: None,
+            clock: RealClock
+        }
+    }
+}
+
+impl<'a, I, E, C> RateLimit<'a, I, E, C> where C : Clock {
+
+    /// The same RateLimit, reading the time from another clock
+    pub fn with_clock<C2>(self, clock: C2) -> RateLimit<'a, I, E, C2> where C2 : Clock {
+        RateLimit {
+            subscriber: self.subscriber,
+            subscription: self.subscription,
+            excess: self.excess,
+            per_ns: self.per_ns,
+            burst: self.burst,
+            tokens: self.tokens,
+            last_ns: self.last_ns,
+            clock: clock
+        }
+    }
+
+    /// Tops the bucket up for the time passed since last time
+    fn refill(&mut self) {
+        let now = self.clock.now_ns();
+        if let Some(last) = self.last_ns {
+            self.tokens = (self.tokens + (now - last) as f64 * self.per_ns).min(self.burst);
+        }
+        self.last_ns = Some(now);
+    }
+}
+
+impl<'a, I, E, C> Publisher<'a> for RateLimit<'a, I, E, C> where C : Clock {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E, C> Subscriber for RateLimit<'a, I, E, C> where C : Clock, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+    default_pass_error!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        self.refill();
+        if self.tokens < 1.0 {
+            match self.excess {
+                Excess::Block => {
+                    let wait = ((1.0 - self.tokens) / self.per_ns).ceil() as u64;
+                    self.clock.sleep_ns(wait);
+                    self.refill();
+                },
+                Excess::Drop => return drop_next!(self),
+                Excess::Error(limited) => {
+                    if let Some(s) = self.subscriber.as_mut() { s.on_error(limited(RateLimited)); }
+                    if let Some(sub) = self.subscription.as_ref() { sub.cancel(); }
+                    self.subscriber = None;
+                    return false
+                }
+            }
+        }
+        self.tokens = (self.tokens - 1.0).max(0.0);
+        pass_next!(self, t)
+    }
+}
+
+/// OnErrorReturn
+/// turns an error into one last item, made from the error by the
+/// supplied function, after which the stream completes as normal
+///
+pub struct OnErrorReturn<'a, I, E, F> where F : Fn(E) -> I {
+    subscriber: Option<Box<Subscriber<Input=I, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    fun: F
+}
+
+impl<'a, I, E, F> OnErrorReturn<'a, I, E, F> where F : Fn(E) -> I {
+
+    pub fn new(f: F) -> OnErrorReturn<'a, I, E, F> {
+        OnErrorReturn {
+            subscriber: None,
+            subscription: None,
+            fun: f
+        }
+    }
+}
+
+impl<'a, I, E, F> Publisher<'a> for OnErrorReturn<'a, I, E, F> where F : Fn(E) -> I {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=I, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E, F> Subscriber for OnErrorReturn<'a, I, E, F> where F : Fn(E) -> I, E : Debug {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+    default_pass_complete!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        pass_next!(self, t)
+    }
+
+    fn on_error(&mut self, err: E) {
+        let t = (self.fun)(err);
+        if pass_next!(self, t) {
+            self.on_complete(false);
+        }
+    }
+}
+
+/// How a Window divides up its stream
+#[derive(Copy, Clone, Debug)]
+pub enum WindowKind {
+    /// back to back windows of this many items
+    Tumbling(usize),
+    /// windows of .0 items, a new one opening every .1 items,
+    /// so they overlap when .1 is smaller than .0
+    Sliding(usize, usize),
+    /// back to back windows, each spanning this much time from its first item
+    Timed(Duration),
+    /// windows which close once no item has arrived for this long
+    Session(Duration)
+}
+
+/// Pane
+/// What an open window gathers its items in. A Vec holds on to them, and is
+/// passed on whole as its window closes. A LivePane hands them straight on to
+/// a Group, which is passed on as its window opens and completed as it closes
+pub trait Pane<I, E> {
+    type Output;
+
+    /// A pane for a window which is opening, with room for this many items
+    fn open(capacity: usize) -> Self;
+
+    fn push(&mut self, t: I);
+
+    /// The number of items pushed so far
+    fn len(&self) -> usize;
+
+    /// Whatever is passed on as the window opens
+    fn opened(&mut self) -> Option<Self::Output>;
+
+    /// Whatever is passed on as the window closes
+    fn close(self, force: bool) -> Option<Self::Output>;
+
+    /// The stream ended in an error while the window was open
+    fn error(self, err: &E);
+}
+
+impl<I, E> Pane<I, E> for Vec<I> {
+    type Output = Vec<I>;
+
+    fn open(capacity: usize) -> Vec<I> {
+        Vec::with_capacity(capacity)
+    }
+
+    fn push(&mut self, t: I) {
+        Vec::push(self, t)
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn opened(&mut self) -> Option<Vec<I>> {
+        None
+    }
+
+    fn close(self, _: bool) -> Option<Vec<I>> {
+        if self.is_empty() { None } else { Some(self) }
+    }
+
+    fn error(self, _: &E) { }
+}
+
+/// LivePane
+/// An open window as a live stream. It is passed on as a Group as soon as
+/// the window opens, the group is sent each item as it arrives, and is
+/// completed as the window closes. As with GroupBy's groups, a group takes
+/// one subscriber, and queues whatever that has not yet asked for
+pub struct LivePane<'a, I, E> {
+    state: Rc<RefCell<GroupState<'a, I, E>>>,
+    count: usize
+}
+
+impl<'a, I, E> Pane<I, E> for LivePane<'a, I, E> where E : Clone {
+    type Output = Group<'a, I, E>;
+
+    fn open(_: usize) -> LivePane<'a, I, E> {
+        LivePane {
+            state: Rc::new(RefCell::new(GroupState::new(0))),
+            count: 0
+        }
+    }
+
+    fn push(&mut self, t: I) {
+        self.count += 1;
+        self.state.borrow_mut().push(t);
+    }
+
+    fn len(&self) -> usize {
+        self.count
+    }
+
+    fn opened(&mut self) -> Option<Group<'a, I, E>> {
+        Some(Group { state: self.state.clone() })
+    }
+
+    fn close(self, force: bool) -> Option<Group<'a, I, E>> {
+        self.state.borrow_mut().end(None, force);
+        None
+    }
+
+    fn error(self, err: &E) {
+        self.state.borrow_mut().end(Some(err.clone()), false);
+    }
+}
+
+/// Window
+/// divides the stream into windows as described by its WindowKind.
+/// Made with new, it passes on the contents of each window whole, as a Vec,
+/// as it closes. Made with live, it passes on each window as a Group as it
+/// opens, which is sent the window's items as they arrive, see LivePane.
+/// A timed or session window which has run out of time closes when it is
+/// ticked, or else when the first item which falls outside of it shows up.
+/// Hung off of a publisher with Ticked, as window and live_window do, it is
+/// ticked as the publisher is polled. What it has to pass on waits until
+/// there is demand for it. Any windows still open are closed when the stream
+/// completes, and the subscriber is completed once they have been passed on.
+/// A negative time span or gap counts as none, so each window closes as the
+/// next item arrives. To treat a Vec window as a stream in its own right,
+/// map the output through window_publisher.
+/// Time is read from a RealClock, unless another clock is given with with_clock
+///
+pub struct Window<'a, I, E, C = RealClock, P = Vec<I>> where I : Clone, C : Clock, P : Pane<I, E> {
+    subscriber: Option<Box<Subscriber<Input=<P as Pane<I, E>>::Output, Error=E> + 'a>>,
+    subscription: Option<Subscription>,
+    kind: WindowKind,
+    open: RingBuf<P>,
+    ready: RingBuf<<P as Pane<I, E>>::Output>,
+    seen: usize,
+    started_ns: u64,
+    last_ns: u64,
+    completed: Option<bool>,
+    clock: C
+}
+
+impl<'a, I, E> Window<'a, I, E, RealClock> where I : Clone {
+
+    /// Passes on each window whole as it closes.
+    /// Panics if the kind gives a window size or step of 0
+    pub fn new(kind: WindowKind) -> Window<'a, I, E> {
+        Window::configured(kind)
+    }
+}
+
+impl<'a, I, E> Window<'a, I, E, RealClock, LivePane<'a, I, E>> where I : Clone, E : Clone {
+
+    /// Passes on each window as a Group as it opens.
+    /// Panics if the kind gives a window size or step of 0
+    pub fn live(kind: WindowKind) -> Window<'a, I, E, RealClock, LivePane<'a, I, E>> {
+        Window::configured(kind)
+    }
+}
+
+impl<'a, I, E, P> Window<'a, I, E, RealClock, P> where I : Clone, P : Pane<I, E> {
+
+    fn configured(kind: WindowKind) -> Window<'a, I, E, RealClock, P> {
+        match kind {
+            WindowKind::Tumbling(0) | WindowKind::Sliding(0, _) | WindowKind::Sliding(_, 0) =>
+                panic!("{:?} would never close a window, sizes and steps must be at least 1", kind),
+            _ => ()
+        }
+        Window {
+            subscriber: None,
+            subscription: None,
+            kind: kind,
+            open: RingBuf::new(),
+            ready: RingBuf::new(),
+            seen: 0,
+            started_ns: 0,
+            last_ns: 0,
+            completed: None,
+            clock: RealClock
+        }
+    }
+}
+
+impl<'a, I, E, C, P> Window<'a, I, E, C, P> where I : Clone, C : Clock, P : Pane<I, E> {
+
+    /// The same Window, reading the time from another clock
+    pub fn with_clock<C2>(self, clock: C2) -> Window<'a, I, E, C2, P> where C2 : Clock {
+        Window {
+            subscriber: self.subscriber,
+            subscription: self.subscription,
+            kind: self.kind,
+            open: self.open,
+            ready: self.ready,
+            seen: self.seen,
+            started_ns: self.started_ns,
+            last_ns: self.last_ns,
+            completed: self.completed,
+            clock: clock
+        }
+    }
+
+    /// When the open timed or session window is due to close, if there is one
+    fn due_ns(&self) -> Option<u64> {
+        if self.open.is_empty() {
+            return None
+        }
+        match self.kind {
+            WindowKind::Timed(span) => Some(self.started_ns + clamped_ns(span)),
+            WindowKind::Session(gap) => Some(self.last_ns + clamped_ns(gap)),
+            _ => None
+        }
+    }
+
+    /// Opens a window, readying whatever is passed on as it does
+    fn open_pane(&mut self, capacity: usize) {
+        let mut pane = <P as Pane<I, E>>::open(capacity);
+        if let Some(o) = pane.opened() {
+            self.ready.push_back(o);
+        }
+        self.open.push_back(pane);
+    }
+
+    /// Closes the oldest window, readying whatever is passed on as it does
+    fn close_pane(&mut self, force: bool) {
+        if let Some(pane) = self.open.pop_front() {
+            if let Some(o) = pane.close(force) {
+                self.ready.push_back(o);
+            }
+        }
+    }
+
+    /// Adds the item to the open windows, opening and closing them as it goes
+    fn push(&mut self, t: I) {
+        match self.kind {
+            WindowKind::Tumbling(size) => self.push_sliding(t, size, size),
+            WindowKind::Sliding(size, skip) => self.push_sliding(t, size, skip),
+            WindowKind::Timed(span) => {
+                let now = self.clock.now_ns();
+                if !self.open.is_empty() && now - self.started_ns >= clamped_ns(span) {
+                    self.close_pane(false);
+                }
+                if self.open.is_empty() {
+                    self.started_ns = now;
+                    self.open_pane(0);
+                }
+                self.open.back_mut().unwrap().push(t);
+            },
+            WindowKind::Session(gap) => {
+                let now = self.clock.now_ns();
+                if !self.open.is_empty() && now - self.last_ns >= clamped_ns(gap) {
+                    self.close_pane(false);
+                }
+                if self.open.is_empty() {
+                    self.open_pane(0);
+                }
+                self.last_ns = now;
+                self.open.back_mut().unwrap().push(t);
+            }
+        }
+    }
+
+    fn push_sliding(&mut self, t: I, size: usize, skip: usize) {
+        if self.seen % skip == 0 {
+            self.open_pane(size);
+        }
+        self.seen += 1;
+        for w in self.open.iter_mut() {
+            w.push(t.clone());
+        }
+        // windows are opened in order, so only the oldest can be full
+        while self.open.front().map_or(false, |w| w.len() >= size) {
+            self.close_pane(false);
+        }
+    }
+
+    /// Passes on what is ready while there is demand for it, the first
+    /// for free if the upstream has already paid for it. Completes the
+    /// subscriber once nothing is left and the stream is over.
+    /// Returns false once the stream has ended
+    fn send_ready(&mut self, paid: bool) -> bool {
+        let mut paid = paid;
+        while !self.ready.is_empty() {
+            if !paid && !self.subscription.as_ref().map_or(false, |s| s.take()) {
+                return true
+            }
+            paid = false;
+            let o = self.ready.pop_front().unwrap();
+            if !pass_next!(self, o) {
+                self.ready.clear();
+                return false
+            }
+        }
+        if paid {
+            // the item opened and closed nothing
+            drop_next!(self);
+        }
+        match self.completed {
+            Some(force) => {
+                if let Some(mut s) = self.subscriber.take() { s.on_complete(force); }
+                false
+            },
+            None => self.subscriber.is_some()
+        }
+    }
+}
+
+impl<'a, I, E, C, P> Publisher<'a> for Window<'a, I, E, C, P> where I : Clone, C : Clock, P : Pane<I, E> {
+    type Output = <P as Pane<I, E>>::Output;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=<P as Pane<I, E>>::Output, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=<P as Pane<I, E>>::Output, Error=E>+'a> = s;
+        self.subscriber = Some(s);
+        if let Some(sub) = self.subscription.clone() {
+            self.subscriber.as_mut().unwrap().on_subscribe(sub);
+        }
+    }
+}
+
+impl<'a, I, E, C, P> Subscriber for Window<'a, I, E, C, P> where I : Clone, C : Clock, E : Debug, P : Pane<I, E> {
+    type Input = I;
+    type Error = E;
+
+    default_pass_subscribe!();
+
+    fn on_next(&mut self, t: I) -> bool {
+        self.push(t);
+        self.send_ready(true)
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.ready.clear();
+        while let Some(pane) = self.open.pop_front() {
+            pane.error(&err);
+        }
+        match self.subscriber.take() {
+            Some(mut s) => s.on_error(err),
+            None => debug!("on_error called but I don't have a subscriber")
+        }
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        if self.subscriber.is_none() {
+            debug!("on_complete called but I don't have a subscriber");
+        }
+        while !self.open.is_empty() {
+            self.close_pane(force);
+        }
+        self.completed = Some(force);
+        self.send_ready(false);
+    }
+}
+
+impl<'a, I, E, C, P> Tick for Window<'a, I, E, C, P> where I : Clone, C : Clock, E : Debug, P : Pane<I, E> {
+
+    /// Closes the timed or session window if it has run out of time,
+    /// and passes on what is ready, as far as there is demand for it
+    fn tick(&mut self) -> bool {
+        if self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+            self.subscriber = None;
+        }
+        if self.subscriber.is_none() {
+            return false
+        }
+        let now = self.clock.now_ns();
+        if self.due_ns().map_or(false, |d| now >= d) {
+            self.close_pane(false);
+        }
+        self.send_ready(false)
+    }
+
+    fn idle(&self) {
+        if self.completed.is_some() || !self.ready.is_empty() {
+            return idle_on(self.subscription.as_ref())
+        }
+        idle(&self.clock, self.due_ns())
+    }
+}
+
+/// A publisher over the contents of a single window
+pub type WindowPublisher<'a, I, E> = IterPublisher<'a, 'a, 'a, I, vec::IntoIter<I>, E>;
+
+/// Turns a Vec window into a publisher of its contents,
+/// which can have processors chained on to it like any other.
+/// For windows which are streams from the start, see Window::live
+pub fn window_publisher<'a, I, E>(w: Vec<I>) -> WindowPublisher<'a, I, E> where I : 'a {
+    IterPublisher::new(w.into_iter())
+}
+
+/// Broadcast
+/// A processor with any number of subscribers. How the items are shared
+/// out between them is up to its OutputStrategy, by default LockStep,
+/// which sends every item to all of them together. A subscriber which
+/// returns false from on_next, or cancels its subscription, is dropped
+/// while the rest carry on. An error goes out to all together, completion
+/// goes out to each once it has been sent whatever was held back for it.
+/// Broadcast asks its upstream for only as much as the strategy can pass on,
+/// for LockStep that is the least any subscriber has asked for. Demand a
+/// subscriber adds later is noticed on the next item, or on try_next, which
+/// also sends on whatever the strategy has held back
+pub struct Broadcast<'a, I, E, S = LockStep<I>> where E : Clone, S : OutputStrategy<I> {
+    outlets: Vec<Outlet<'a, I, E>>,
+    strategy: S,
+    subscription: Option<Subscription>,
+    next_index: usize,
+    finished: bool,
+    error: Option<E>
+}
+
+impl<'a, I, E> Broadcast<'a, I, E, LockStep<I>> where I : Clone, E : Clone {
+
+    pub fn new() -> Broadcast<'a, I, E, LockStep<I>> {
+        Broadcast::with_strategy(LockStep::new())
+    }
+}
+
+impl<'a, I, E, S> Broadcast<'a, I, E, S> where E : Clone, S : OutputStrategy<I> {
+
+    pub fn with_strategy(strategy: S) -> Broadcast<'a, I, E, S> {
+        Broadcast {
+            outlets: Vec::new(),
+            strategy: strategy,
+            subscription: None,
+            next_index: 0,
+            finished: false,
+            error: None
+        }
+    }
+
+    /// The number of subscribers still attached
+    pub fn len(&self) -> usize {
+        self.outlets.len()
+    }
+
+    /// Adds a subscriber, which has the backlog queued for it alone. It is
+    /// sent the backlog as it asks for it, and nothing else until it has had
+    /// all of it. A subscriber arriving after the stream has ended is sent
+    /// the backlog in the same way, then the error or completion which ended it
+    pub fn subscribe_with<B>(&mut self, mut s: Box<Subscriber<Input=I, Error=E> + 'a>, backlog: B)
+    where B : Iterator<Item=I>
+    {
+        let sub = Subscription::new(self.next_index);
+        self.next_index += 1;
+        s.on_subscribe(sub.clone());
+        let mut outlet = Outlet::new(s, sub);
+        outlet.queue().extend(backlog);
+        outlet.send_queued();
+        if self.finished {
+            match self.error.clone() {
+                Some(e) => outlet.error_queued(e),
+                None => outlet.complete(false)
+            }
+        }
+        if !outlet.is_closed() {
+            self.outlets.push(outlet);
+        }
+        if !self.finished {
+            self.request_more();
+        }
+    }
+
+    /// Sends each outlet what is queued for it alone, which goes out
+    /// ahead of anything the strategy has for it
+    fn send_queued(&mut self) {
+        for o in self.outlets.iter_mut() {
+            o.send_queued();
+        }
+    }
+
+    /// Tops up what the upstream has been asked for, to what the strategy can pass on
+    fn request_more(&self) {
+        if let Some(sub) = self.subscription.as_ref() {
+            let want = self.strategy.demand(self.outlets.as_slice());
+            let asked = sub.demand();
+            if want > asked {
+                sub.request(want - asked);
+            }
+        }
+    }
+
+    /// Drops the outlets whose subscribers have gone away, returns
+    /// false if that leaves nobody, when there was somebody before
+    fn prune(&mut self, before: usize) -> bool {
+        self.outlets.retain(|o| !o.is_closed());
+        if before > 0 && self.outlets.is_empty() {
+            if let Some(sub) = self.subscription.as_ref() { sub.cancel(); }
+            return false
+        }
+        true
+    }
+}
+
+impl<'a, I, E, S> Publisher<'a> for Broadcast<'a, I, E, S> where E : Clone, S : OutputStrategy<I> {
+    type Output = I;
+    type Error = E;
+
+    /// Adds a subscriber, unlike other processors the existing ones are kept
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        self.subscribe_with(s, Vec::new().into_iter());
+    }
+
+    /// Sends on whatever is held back for subscribers which have since
+    /// asked for more. Once the stream has ended, returns false when
+    /// every subscriber has been sent all it is owed, and the end
+    fn try_next(&mut self) -> bool {
+        let before = self.outlets.len();
+        self.send_queued();
+        self.strategy.flush(self.outlets.as_mut_slice());
+        let more = self.prune(before);
+        if self.finished {
+            return !self.outlets.is_empty()
+        }
+        if more { self.request_more(); }
+        more
+    }
+}
+
+impl<'a, I, E, S> Subscriber for Broadcast<'a, I, E, S> where E : Clone + Debug, S : OutputStrategy<I> {
+    type Input = I;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        self.subscription = Some(sub);
+        self.request_more();
+    }
+
+    fn on_next(&mut self, t: I) -> bool {
+        if self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+            for o in self.outlets.iter_mut() { o.close(); }
+            self.outlets.clear();
+            return false
+        }
+        let before = self.outlets.len();
+        self.send_queued();
+        self.strategy.push(t, self.outlets.as_mut_slice());
+        // once everyone who was listening has gone, there is no point carrying on
+        let more = self.prune(before);
+        if more { self.request_more(); }
+        more
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.finished = true;
+        for o in self.outlets.iter_mut() {
+            o.error(err.clone());
+        }
+        self.outlets.clear();
+        self.error = Some(err);
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        self.finished = true;
+        self.strategy.drain(self.outlets.as_mut_slice());
+        for o in self.outlets.iter_mut() {
+            o.complete(force);
+        }
+        // those with a backlog still to be sent are kept until they have it
+        self.outlets.retain(|o| !o.is_closed());
+    }
+}
+
+// What a GroupBy or live Window and the Group it handed out share
+struct GroupState<'a, I, E> {
+    outlet: Option<Outlet<'a, I, E>>,
+    early: RingBuf<I>,
+    ended: bool,
+    error: Option<E>,
+    last_ns: u64
+}
+
+impl<'a, I, E> GroupState<'a, I, E> where E : Clone {
+
+    fn new(now: u64) -> GroupState<'a, I, E> {
+        GroupState {
+            outlet: None,
+            early: RingBuf::new(),
+            ended: false,
+            error: None,
+            last_ns: now
+        }
+    }
+
+    fn push(&mut self, t: I) {
+        match self.outlet.as_mut() {
+            Some(o) => {
+                if !o.is_closed() {
+                    o.queue().push_back(t);
+                    o.send_queued();
+                }
+            },
+            // nobody has subscribed to the group yet, so hold on to it
+            None => self.early.push_back(t)
+        }
+    }
+
+    /// How many more items the group can queue before it is full,
+    /// one whose subscriber has gone away drops them, so is never full
+    fn room(&self, capacity: usize) -> usize {
+        let queued = match self.outlet.as_ref() {
+            Some(o) if o.is_closed() => 0,
+            Some(o) => o.queued(),
+            None => self.early.len()
+        };
+        capacity.saturating_sub(queued)
+    }
+
+    fn end(&mut self, err: Option<E>, force: bool) {
+        self.ended = true;
+        match self.outlet.as_mut() {
+            Some(o) => match err {
+                Some(e) => o.error(e),
+                None => { o.send_queued(); o.complete(force) }
+            },
+            None => self.error = err
+        }
+    }
+}
+
+/// Group
+/// The items of a single key, as handed out by a GroupBy,
+/// or of a single window, as handed out by a live Window.
+/// Takes one subscriber, which is sent anything the group was given before
+/// it subscribed, then the rest as it asks for them. Anything it has not yet
+/// asked for is queued, try_next sends on what it has since asked for
+pub struct Group<'a, I, E> {
+    state: Rc<RefCell<GroupState<'a, I, E>>>
+}
+
+impl<'a, I, E> Publisher<'a> for Group<'a, I, E> where E : Clone {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, mut s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let mut state = self.state.borrow_mut();
+        if state.outlet.is_some() {
+            error!("A group only takes one subscriber");
+            return
+        }
+        let sub = Subscription::new(0);
+        s.on_subscribe(sub.clone());
+        let mut outlet = Outlet::new(s, sub);
+        while let Some(t) = state.early.pop_front() {
+            outlet.queue().push_back(t);
+        }
+        outlet.send_queued();
+        state.outlet = Some(outlet);
+        if state.ended {
+            let err = state.error.take();
+            state.end(err, false);
+        }
+    }
+
+    fn try_next(&mut self) -> bool {
+        let mut state = self.state.borrow_mut();
+        let ended = state.ended;
+        match state.outlet.as_mut() {
+            Some(o) => { o.send_queued(); !o.is_closed() },
+            None => !ended
+        }
+    }
+}
+
+/// GroupBy
+/// splits the stream into a Group for each key, as given by the key
+/// function, passing on (key, group) the first time each key is seen.
+/// Every later item with the same key goes to that group instead.
+/// Errors and completion go to every group, as well as the subscriber.
+/// With expiring, a group which has had nothing for the given time is
+/// completed and forgotten, and the next item with its key starts a new one.
+/// Expiry is checked as items arrive, and whenever it is ticked. Hung off of
+/// a publisher with Ticked, as group_by_expiring does, it is ticked as the
+/// publisher is polled, so a group can expire while its upstream is quiet.
+/// Held on its own, it is ticked by try_next.
+/// A new group is only passed on once it has been asked for, until then
+/// nothing more is asked of the upstream. Each group queues what its
+/// subscriber has not yet asked for, up to capacity items, by default
+/// GROUP_CAPACITY. The upstream is asked for no more than the fullest group
+/// has room for, so a full group holds up the rest until it is drained.
+/// For instance, to give each connection its own chain, group the ProtoMsgs
+/// from a NetStreamer by their Token
+///
+/// ```ignore
+/// let mut groups = GroupBy::new(|m: &ProtoMsg<U>| m.1);
+/// ```
+pub struct GroupBy<'a, I, K, E, F, C = RealClock>
+where K : Hash + Eq + Clone, E : Clone, F : Fn(&I) -> K, C : Clock
+{
+    key_fn: F,
+    groups: HashMap<K, Rc<RefCell<GroupState<'a, I, E>>>>,
+    ready: RingBuf<(K, Group<'a, I, E>)>,
+    capacity: usize,
+    completed: Option<bool>,
+    expiry_ns: Option<u64>,
+    clock: C,
+    subscriber: Option<Box<Subscriber<Input=(K, Group<'a, I, E>), Error=E> + 'a>>,
+    // the downstream's, the upstream's is subscription
+    outer: Subscription,
+    subscription: Option<Subscription>
+}
+
+/// How many items a group queues for its subscriber, unless told otherwise
+pub const GROUP_CAPACITY : usize = 1024;
+
+impl<'a, I, K, E, F> GroupBy<'a, I, K, E, F, RealClock>
+where K : Hash + Eq + Clone, E : Clone, F : Fn(&I) -> K
+{
+    pub fn new(key_fn: F) -> GroupBy<'a, I, K, E, F, RealClock> {
+        GroupBy {
+            key_fn: key_fn,
+            groups: HashMap::new(),
+            ready: RingBuf::new(),
+            capacity: GROUP_CAPACITY,
+            completed: None,
+            expiry_ns: None,
+            clock: RealClock,
+            subscriber: None,
+            outer: Subscription::new(0),
+            subscription: None
+        }
+    }
+
+    /// Completes and forgets groups which have had nothing for idle
+    pub fn expiring(key_fn: F, idle: Duration) -> GroupBy<'a, I, K, E, F, RealClock> {
+        let mut g = GroupBy::new(key_fn);
+        g.expiry_ns = Some(clamped_ns(idle));
+        g
+    }
+}
+
+impl<'a, I, K, E, F, C> GroupBy<'a, I, K, E, F, C>
+where K : Hash + Eq + Clone, E : Clone, F : Fn(&I) -> K, C : Clock
+{
+    /// The same GroupBy, reading the time from another clock
+    pub fn with_clock<C2>(self, clock: C2) -> GroupBy<'a, I, K, E, F, C2> where C2 : Clock {
+        GroupBy {
+            key_fn: self.key_fn,
+            groups: self.groups,
+            ready: self.ready,
+            capacity: self.capacity,
+            completed: self.completed,
+            expiry_ns: self.expiry_ns,
+            clock: clock,
+            subscriber: self.subscriber,
+            outer: self.outer,
+            subscription: self.subscription
+        }
+    }
+
+    /// The same GroupBy, with each group queueing up to capacity items
+    pub fn with_capacity(mut self, capacity: usize) -> GroupBy<'a, I, K, E, F, C> {
+        if capacity == 0 {
+            panic!("A GroupBy needs room for at least one item in each group")
+        }
+        self.capacity = capacity;
+        self
+    }
+
+    /// The number of groups still open
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Tops up what the upstream has been asked for, to what the fullest
+    /// group has room for. Nothing more is asked for while a new group
+    /// waits to be passed on
+    fn request_more(&self) {
+        let up = match self.subscription.as_ref() { Some(up) => up, None => return };
+        if !self.ready.is_empty() || self.completed.is_some() {
+            return
+        }
+        let want = self.groups.values().map(|g| g.borrow().room(self.capacity)).min().unwrap_or(self.capacity);
+        let asked = up.demand();
+        if want > asked {
+            up.request(want - asked);
+        }
+    }
+
+    /// Passes on as many new groups as have been asked for, then the
+    /// completion once they are all out. Returns false once the subscriber
+    /// has stopped, or been completed
+    fn send_ready(&mut self) -> bool {
+        while !self.ready.is_empty() && self.outer.take() {
+            let (k, g) = self.ready.pop_front().unwrap();
+            let more = match self.subscriber.as_mut() {
+                Some(s) => s.on_next((k, g)),
+                None => true
+            };
+            if !more {
+                self.release();
+                return false
+            }
+        }
+        if let (true, Some(force)) = (self.ready.is_empty(), self.completed) {
+            if let Some(mut s) = self.subscriber.take() {
+                s.on_complete(force);
+            }
+            return false
+        }
+        true
+    }
+
+    /// The subscriber has gone, so the upstream is told to stop
+    fn release(&mut self) {
+        if let Some(up) = self.subscription.as_ref() { up.cancel(); }
+        self.end(None, true);
+        self.ready.clear();
+        self.subscriber = None;
+    }
+
+    fn expire(&mut self, now: u64) {
+        let idle = match self.expiry_ns { Some(i) => i, None => return };
+        let stale: Vec<K> = self.groups.iter()
+            .filter(|&(_, g)| g.borrow().last_ns + idle <= now)
+            .map(|(k, _)| k.clone())
+            .collect();
+        for k in stale.into_iter() {
+            if let Some(g) = self.groups.remove(&k) {
+                g.borrow_mut().end(None, false);
+            }
+        }
+    }
+
+    fn end(&mut self, err: Option<E>, force: bool) {
+        for (_, g) in self.groups.drain() {
+            g.borrow_mut().end(err.clone(), force);
+        }
+    }
+}
+
+impl<'a, I, K, E, F, C> Publisher<'a> for GroupBy<'a, I, K, E, F, C>
+where K : Hash + Eq + Clone, E : Clone, F : Fn(&I) -> K, C : Clock
+{
+    type Output = (K, Group<'a, I, E>);
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=(K, Group<'a, I, E>), Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=(K, Group<'a, I, E>), Error=E>+'a> = s;
+        self.outer = Subscription::new(0);
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.outer.clone());
+    }
+
+    /// Expires idle groups, and passes on new groups which have since been asked for
+    fn try_next(&mut self) -> bool {
+        self.tick()
+    }
+}
+
+impl<'a, I, K, E, F, C> Tick for GroupBy<'a, I, K, E, F, C>
+where K : Hash + Eq + Clone, E : Clone, F : Fn(&I) -> K, C : Clock
+{
+    /// Expires idle groups, passes on new groups which have since been
+    /// asked for, and asks for more once the groups have room for it
+    fn tick(&mut self) -> bool {
+        if self.outer.is_cancelled() || self.subscription.as_ref().map_or(false, |s| s.is_cancelled()) {
+            self.release();
+            return false
+        }
+        if self.subscriber.is_none() {
+            return false
+        }
+        let now = self.clock.now_ns();
+        self.expire(now);
+        let more = self.send_ready();
+        if more { self.request_more(); }
+        more
+    }
+
+    fn idle(&self) {
+        if !self.ready.is_empty() {
+            return idle_on(Some(&self.outer))
+        }
+        let due = self.expiry_ns.and_then(|after| {
+            self.groups.values().map(|g| g.borrow().last_ns + after).min()
+        });
+        idle(&self.clock, due)
+    }
+}
+
+impl<'a, I, K, E, F, C> Subscriber for GroupBy<'a, I, K, E, F, C>
+where K : Hash + Eq + Clone, E : Clone + Debug, F : Fn(&I) -> K, C : Clock
+{
+    type Input = I;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        self.subscription = Some(sub);
+        self.request_more();
+    }
+
+    fn on_next(&mut self, t: I) -> bool {
+        if self.outer.is_cancelled() {
+            self.release();
+            return false
+        }
+        let now = self.clock.now_ns();
+        self.expire(now);
+        let key = (self.key_fn)(&t);
+        let found = self.groups.get(&key).map(|g| g.clone());
+        let state = match found {
+            Some(g) => g,
+            None => {
+                // it is handed out once it has been asked for
+                let state = Rc::new(RefCell::new(GroupState::new(now)));
+                self.groups.insert(key.clone(), state.clone());
+                self.ready.push_back((key, Group { state: state.clone() }));
+                state
+            }
+        };
+        {
+            let mut g = state.borrow_mut();
+            g.last_ns = now;
+            g.push(t);
+        }
+        let more = self.send_ready();
+        if more { self.request_more(); }
+        more
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.end(Some(err.clone()), false);
+        self.ready.clear();
+        if let Some(mut s) = self.subscriber.take() {
+            s.on_error(err);
+        }
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        self.end(None, force);
+        self.completed = Some(force);
+        self.send_ready();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use processor::{Buffer, Window, WindowKind, ThrottleFirst, RateLimit, GroupBy, Materialize};
+    use publisher::{IterPublisher, Coupler, Ticked, Chain, PublisherExt};
+    use subscriber::Collect;
+    use reactive::{Publisher, Subscriber, Subscription, Notification};
+    use scheduler::VirtualScheduler;
+    use error::RateLimited;
+    use std::time::Duration;
+    use std::sync::mpsc::channel;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn buffer_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut buf = Box::new(Buffer::<u32, ()>::with_time(Duration::seconds(1)).with_clock(sched.clock()));
+            buf.subscribe(Box::new(Collect::new(&mut v)));
+            buf.on_next(1);
+            clock.advance(Duration::milliseconds(500));
+            buf.on_next(2);
+            clock.advance(Duration::milliseconds(500));
+            buf.on_next(3);
+            buf.on_next(4);
+            buf.on_complete(false);
+        }
+        assert_eq!(*v, vec![vec![1, 2], vec![3, 4]]);
+    }
+
+    #[test]
+    fn buffer_flushes_on_a_quiet_upstream() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = Ticked::new(Coupler::<u32, ()>::new(rx), Buffer::<u32, ()>::with_time(Duration::seconds(1)).with_clock(sched.clock()));
+            t.subscribe(Box::new(Collect::new(&mut v)));
+            tx.send(1).unwrap();
+            tx.send(2).unwrap();
+            t.try_next();
+            t.try_next();
+            clock.advance(Duration::seconds(1));
+            t.try_next();
+            tx.send(3).unwrap();
+            t.try_next();
+            drop(tx);
+            while t.try_next() {}
+        }
+        assert_eq!(*v, vec![vec![1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn timed_window_closes_on_a_quiet_upstream() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = Ticked::new(Coupler::<u32, ()>::new(rx), Window::<u32, ()>::new(WindowKind::Timed(Duration::seconds(1))).with_clock(sched.clock()));
+            t.subscribe(Box::new(Collect::new(&mut v)));
+            tx.send(1).unwrap();
+            tx.send(2).unwrap();
+            t.try_next();
+            t.try_next();
+            clock.advance(Duration::milliseconds(999));
+            t.try_next();
+            clock.advance(Duration::milliseconds(1));
+            t.try_next();
+            tx.send(3).unwrap();
+            t.try_next();
+            drop(tx);
+            while t.try_next() {}
+        }
+        assert_eq!(*v, vec![vec![1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn session_window_closes_after_the_gap() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = Ticked::new(Coupler::<u32, ()>::new(rx), Window::<u32, ()>::new(WindowKind::Session(Duration::milliseconds(500))).with_clock(sched.clock()));
+            t.subscribe(Box::new(Collect::new(&mut v)));
+            tx.send(1).unwrap();
+            t.try_next();
+            clock.advance(Duration::milliseconds(300));
+            tx.send(2).unwrap();
+            t.try_next();
+            clock.advance(Duration::milliseconds(300));
+            t.try_next();
+            clock.advance(Duration::milliseconds(200));
+            t.try_next();
+            tx.send(3).unwrap();
+            tx.send(4).unwrap();
+            t.try_next();
+            t.try_next();
+            drop(tx);
+            while t.try_next() {}
+        }
+        assert_eq!(*v, vec![vec![1, 2], vec![3, 4]]);
+    }
+
+    // Keeps hold of whatever it is sent, such as the groups a GroupBy
+    // or live Window hands out, so they can be subscribed to later
+    struct Keep<T> {
+        kept: Rc<RefCell<Vec<T>>>
+    }
+
+    impl<T> Subscriber for Keep<T> {
+        type Input = T;
+        type Error = ();
+
+        fn on_next(&mut self, t: T) -> bool {
+            self.kept.borrow_mut().push(t);
+            true
+        }
+    }
+
+    #[test]
+    fn live_window_is_handed_out_as_it_opens() {
+        let mut first = Box::new(Vec::new());
+        let mut second = Box::new(Vec::new());
+        {
+            let kept = Rc::new(RefCell::new(Vec::new()));
+            let mut t = IterPublisher::<_, _, ()>::new(range(0u32, 4)).live_window(WindowKind::Tumbling(2));
+            t.subscribe(Box::new(Keep { kept: kept.clone() }));
+            // the first window is handed out with its first item,
+            // and subscribed to while it is still open
+            t.next();
+            let mut g0 = kept.borrow_mut().remove(0);
+            g0.subscribe(Box::new(Collect::new(&mut first)));
+            t.run();
+            let mut g1 = kept.borrow_mut().remove(0);
+            g1.subscribe(Box::new(Collect::new(&mut second)));
+            assert!(kept.borrow().is_empty());
+        }
+        assert_eq!(*first, vec![0, 1]);
+        assert_eq!(*second, vec![2, 3]);
+    }
+
+    #[test]
+    fn throttle_first_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut tf = ThrottleFirst::<u32, (), _>::with_clock(Duration::seconds(1), sched.clock());
+            tf.subscribe(Box::new(Collect::new(&mut v)));
+            tf.on_next(1);
+            clock.advance(Duration::milliseconds(500));
+            tf.on_next(2);
+            clock.advance(Duration::milliseconds(500));
+            tf.on_next(3);
+            tf.on_next(4);
+            tf.on_complete(false);
+        }
+        assert_eq!(*v, vec![1, 3]);
+    }
+
+    #[test]
+    fn throttle_first_with_a_negative_window() {
+        let sched = VirtualScheduler::new();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut tf = ThrottleFirst::<u32, (), _>::with_clock(Duration::seconds(-1), sched.clock());
+            tf.subscribe(Box::new(Collect::new(&mut v)));
+            tf.on_next(1);
+            tf.on_next(2);
+            tf.on_complete(false);
+        }
+        assert_eq!(*v, vec![1, 2]);
+    }
+
+    #[test]
+    fn rate_limit_blocks_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut rl = RateLimit::<u32, ()>::blocking(2, 1).with_clock(sched.clock());
+            rl.subscribe(Box::new(Collect::new(&mut v)));
+            rl.on_next(1);
+            assert_eq!(sched.now_ns(), 0);
+            // the bucket is empty, so this waits half a second for room
+            rl.on_next(2);
+            assert_eq!(sched.now_ns(), 500_000_000);
+            rl.on_complete(false);
+        }
+        assert_eq!(*v, vec![1, 2]);
+    }
+
+    #[test]
+    fn rate_limit_drops_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut rl = RateLimit::<u32, ()>::dropping(1, 2).with_clock(sched.clock());
+            rl.subscribe(Box::new(Collect::new(&mut v)));
+            rl.on_next(1);
+            rl.on_next(2);
+            rl.on_next(3);
+            clock.advance(Duration::seconds(1));
+            rl.on_next(4);
+            rl.on_next(5);
+            rl.on_complete(false);
+        }
+        assert_eq!(*v, vec![1, 2, 4]);
+        assert_eq!(sched.now_ns(), 1_000_000_000);
+    }
+
+    #[test]
+    fn rate_limit_errors_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = Chain::new(IterPublisher::<_, _, RateLimited>::new(range(0u32, 5)),
+                                       RateLimit::<u32, RateLimited>::erroring(1, 2).with_clock(sched.clock()))
+                .materialize();
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![Notification::Next(0), Notification::Next(1),
+                            Notification::Error(RateLimited), Notification::Complete]);
+    }
+
+    #[test]
+    #[should_fail]
+    fn rate_limit_of_zero() {
+        RateLimit::<u32, ()>::dropping(0, 1);
+    }
+
+    #[test]
+    fn group_takes_a_late_subscriber() {
+        let mut evens = Box::new(Vec::new());
+        let mut odds = Box::new(Vec::new());
+        {
+            let kept = Rc::new(RefCell::new(Vec::new()));
+            {
+                let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 6)).group_by(|i: &u32| *i % 2);
+                chain.subscribe(Box::new(Keep { kept: kept.clone() }));
+                chain.run();
+            }
+            let (k0, mut g0) = kept.borrow_mut().remove(0);
+            let (k1, mut g1) = kept.borrow_mut().remove(0);
+            assert_eq!((k0, k1), (0, 1));
+            g0.subscribe(Box::new(Collect::new(&mut evens)));
+            g1.subscribe(Box::new(Collect::new(&mut odds)));
+        }
+        assert_eq!(*evens, vec![0, 2, 4]);
+        assert_eq!(*odds, vec![1, 3, 5]);
+    }
+
+    #[test]
+    fn group_by_passes_an_error_to_every_group() {
+        let mut evens = Box::new(Vec::new());
+        let mut odds = Box::new(Vec::new());
+        {
+            let kept = Rc::new(RefCell::new(Vec::new()));
+            let (mut g0, mut g1) = {
+                let mut chain = IterPublisher::new(vec![Notification::Next(0u32), Notification::Next(1), Notification::Error(())].into_iter())
+                    .dematerialize()
+                    .group_by(|i: &u32| *i % 2);
+                chain.subscribe(Box::new(Keep { kept: kept.clone() }));
+                // the first group is subscribed to before the error, the second after
+                chain.next();
+                let (_, mut g0) = kept.borrow_mut().remove(0);
+                let mut m = Materialize::new();
+                m.subscribe(Box::new(Collect::new(&mut evens)));
+                g0.subscribe(Box::new(m));
+                chain.run();
+                let (_, g1) = kept.borrow_mut().remove(0);
+                (g0, g1)
+            };
+            let mut m = Materialize::new();
+            m.subscribe(Box::new(Collect::new(&mut odds)));
+            g1.subscribe(Box::new(m));
+            assert!(!g0.try_next());
+        }
+        assert_eq!(*evens, vec![Notification::Next(0), Notification::Error(()), Notification::Complete]);
+        assert_eq!(*odds, vec![Notification::Next(1), Notification::Error(()), Notification::Complete]);
+    }
+
+    #[test]
+    fn group_by_asks_for_no_more_than_a_group_has_room_for() {
+        let mut evens = Box::new(Vec::new());
+        {
+            let kept = Rc::new(RefCell::new(Vec::new()));
+            let mut gb = GroupBy::new(|i: &u32| *i % 2).with_capacity(2);
+            gb.subscribe(Box::new(Keep { kept: kept.clone() }));
+            let up = Subscription::new(0);
+            gb.on_subscribe(up.clone());
+            assert_eq!(up.demand(), 2);
+            up.take();
+            gb.on_next(0);
+            assert_eq!(up.demand(), 1);
+            up.take();
+            gb.on_next(2);
+            // the even group is full until somebody takes what it holds
+            assert_eq!(up.demand(), 0);
+            let (_, mut g0) = kept.borrow_mut().remove(0);
+            g0.subscribe(Box::new(Collect::new(&mut evens)));
+            assert!(gb.try_next());
+            assert_eq!(up.demand(), 2);
+        }
+        assert_eq!(*evens, vec![0, 2]);
+    }
+
+    #[test]
+    fn group_by_hands_out_a_group_once_asked() {
+        let mut groups = Box::new(Vec::new());
+        {
+            let mut gb = GroupBy::new(|i: &u32| *i % 2);
+            gb.subscribe(Box::new(Collect::<_, ()>::with_demand(&mut groups, 1)));
+            let up = Subscription::new(0);
+            gb.on_subscribe(up.clone());
+            gb.on_next(0);
+            gb.on_next(1);
+            gb.on_complete(false);
+            // the odd group is still owed, so it is not over yet
+            assert!(gb.try_next());
+        }
+        let keys: Vec<u32> = groups.iter().map(|&(k, _)| k).collect();
+        assert_eq!(keys, vec![0]);
+    }
+
+    #[test]
+    #[should_fail]
+    fn group_by_of_zero_capacity() {
+        GroupBy::<u32, u32, (), _>::new(|i: &u32| *i % 2).with_capacity(0);
+    }
+
+    #[test]
+    fn group_expires_on_a_quiet_upstream() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        let kept = Rc::new(RefCell::new(Vec::new()));
+        {
+            let gb = GroupBy::expiring(|i: &u32| *i % 2, Duration::seconds(1)).with_clock(sched.clock());
+            let mut t = Ticked::new(Coupler::<u32, ()>::new(rx), gb);
+            t.subscribe(Box::new(Keep { kept: kept.clone() }));
+            tx.send(0).unwrap();
+            tx.send(1).unwrap();
+            t.try_next();
+            t.try_next();
+            clock.advance(Duration::milliseconds(500));
+            tx.send(2).unwrap();
+            t.try_next();
+            clock.advance(Duration::milliseconds(500));
+            t.try_next();
+            // the odd group has been quiet for a second, the even one only half
+            assert!(kept.borrow_mut()[0].1.try_next());
+            assert!(!kept.borrow_mut()[1].1.try_next());
+            tx.send(3).unwrap();
+            t.try_next();
+            drop(tx);
+            while t.try_next() {}
+        }
+        let keys: Vec<u32> = kept.borrow().iter().map(|&(k, _)| k).collect();
+        assert_eq!(keys, vec![0, 1, 1]);
+    }
+}
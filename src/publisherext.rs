@@ -2,31 +2,1067 @@
 //
 // This software may be modified and distributed under the terms
 // of the MIT license.  See the LICENSE file for details.
-use reactive::{Publisher, Subscriber, Processor};
+use reactive::{Publisher, Subscriber, Notification};
 use processorimpl::*;
-use std::sync::Arc;
-use std::cell::RefCell;
+use publisherimpl::{Zip, CombineLatest, Merge, Debounce, Sample, ThrottleLast, Retry, OnErrorResume, Timeout, Ticked};
+use error::{TimedOut, RateLimited};
+use std::error::FromError;
+use sendable::Sendable;
+use std::fmt::Debug;
+use std::iter::IntoIterator;
+use std::time::Duration;
+use std::hash::Hash;
+use scheduler::RealClock;
 
-/*
-pub trait PublisherExt<'p> : Publisher<'p> + Sized {
+/// Chain
+/// A publisher with a processor hung off of it which has not been wired up yet.
+/// Subscribing to the chain subscribes the processor, then subscribes the
+/// processor to the publisher, so the chain is wired back to front exactly
+/// as it would be by hand. Pulling on the chain pulls on the publisher at its head
+pub struct Chain<P, S> {
+    upstream: P,
+    stage: Option<S>
+}
+
+impl<P, S> Chain<P, S> {
+    pub fn new(upstream: P, stage: S) -> Chain<P, S> {
+        Chain {
+            upstream: upstream,
+            stage: Some(stage)
+        }
+    }
+}
+
+impl<'a, P, S> Publisher<'a> for Chain<P, S>
+where P : Publisher<'a>,
+      S : Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> +
+          Publisher<'a, Error=<P as Publisher<'a>>::Error> + 'a
+{
+    type Output = <S as Publisher<'a>>::Output;
+    type Error = <P as Publisher<'a>>::Error;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=<S as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>) {
+        match self.stage.take() {
+            Some(mut stage) => {
+                stage.subscribe(s);
+                self.upstream.subscribe(Box::new(stage));
+            },
+            None => panic!("subscribe called twice on a chain, its processor has already been handed upstream")
+        }
+    }
+
+    fn next(&mut self) -> bool {
+        self.upstream.next()
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.upstream.try_next()
+    }
+}
+
+/// PublisherExt
+/// Builder methods for attaching processors to a publisher, in the style of Iterator
+///
+/// ```ignore
+/// let mut chain = IterPublisher::new(it).map(|i| i * 10).enumerate().take(5);
+/// chain.subscribe(Box::new(Collect::new(&mut v)));
+/// chain.run();
+/// ```
+pub trait PublisherExt<'a> : Publisher<'a> + Sized {
+
+    /// see Map
+    fn map<O, F>(self, f: F) -> Chain<Self, Map<'a, <Self as Publisher<'a>>::Output, O, <Self as Publisher<'a>>::Error, F>>
+    where F : Fn(<Self as Publisher<'a>>::Output) -> O
+    {
+        Chain::new(self, Map::new(f))
+    }
+
+    /// see MapVal1
+    fn map_val1<'c, V, O, F>(self, v: V, f: F) -> Chain<Self, MapVal1<'a, 'c, <Self as Publisher<'a>>::Output, V, O, <Self as Publisher<'a>>::Error, F>>
+    where O : 'c, V : Clone, F : Fn(<Self as Publisher<'a>>::Output, &V) -> O
+    {
+        Chain::new(self, MapVal1::new(v, f))
+    }
+
+    /// see Reduce
+    fn reduce<'c, V, O, F>(self, initial: V, f: F) -> Chain<Self, Reduce<'a, 'c, <Self as Publisher<'a>>::Output, V, O, <Self as Publisher<'a>>::Error, F>>
+    where O : 'c, V : Copy, F : Fn(V, <Self as Publisher<'a>>::Output) -> (V, O)
+    {
+        Chain::new(self, Reduce::new(initial, f))
+    }
+
+    /// see Enumerate
+    fn enumerate(self) -> Chain<Self, Enumerate<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>> {
+        Chain::new(self, Enumerate::new())
+    }
+
+    /// see Take
+    fn take(self, max: usize) -> Chain<Self, Take<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>> {
+        Chain::new(self, Take::new(max))
+    }
+
+    /// see Do, named after Iterator's equivalent since do is a keyword
+    fn inspect<F>(self, f: F) -> Chain<Self, Do<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error, F>>
+    where F : Fn(&<Self as Publisher<'a>>::Output) -> ()
+    {
+        Chain::new(self, Do::new(f))
+    }
+
+    /// see Tee
+    fn tee<Q>(self, tx: Q) -> Chain<Self, Tee<'a, Q, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>>
+    where <Self as Publisher<'a>>::Output : Send + Clone,
+          Q : Sendable<Item=<Self as Publisher<'a>>::Output>
+    {
+        Chain::new(self, Tee::new(tx))
+    }
+
+    /// see Unzip
+    fn unzip<I>(self) -> Ticked<Self, Unzip<'a, I, <Self as Publisher<'a>>::Error>>
+    where Self : Publisher<'a, Output=(I, I)>
+    {
+        Ticked::new(self, Unzip::new())
+    }
+
+    /// see DoDebug
+    fn debug(self) -> Chain<Self, DoDebug<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>>
+    where <Self as Publisher<'a>>::Output : Debug
+    {
+        Chain::new(self, DoDebug::new())
+    }
+
+    /// see DebugWhile
+    fn debug_while<F>(self, f: F) -> Chain<Self, DebugWhile<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error, F>>
+    where <Self as Publisher<'a>>::Output : Debug + 'a,
+          F : Fn(&<Self as Publisher<'a>>::Output) -> bool
+    {
+        Chain::new(self, DebugWhile::new(f))
+    }
+
+    /// see Filter
+    fn filter<F>(self, f: F) -> Chain<Self, Filter<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error, F>>
+    where F : Fn(&<Self as Publisher<'a>>::Output) -> bool
+    {
+        Chain::new(self, Filter::new(f))
+    }
+
+    /// see FilterMap
+    fn filter_map<O, F>(self, f: F) -> Chain<Self, FilterMap<'a, <Self as Publisher<'a>>::Output, O, <Self as Publisher<'a>>::Error, F>>
+    where F : Fn(<Self as Publisher<'a>>::Output) -> Option<O>
+    {
+        Chain::new(self, FilterMap::new(f))
+    }
+
+    /// see Distinct
+    fn distinct(self) -> Chain<Self, Distinct<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error, fn(&<Self as Publisher<'a>>::Output) -> <Self as Publisher<'a>>::Output>>
+    where <Self as Publisher<'a>>::Output : Hash + Eq + Clone
+    {
+        Chain::new(self, Distinct::new())
+    }
+
+    /// see Distinct::by
+    fn distinct_by<K, F>(self, key_fn: F) -> Chain<Self, Distinct<'a, <Self as Publisher<'a>>::Output, K, <Self as Publisher<'a>>::Error, F>>
+    where K : Hash + Eq + Clone, F : Fn(&<Self as Publisher<'a>>::Output) -> K
+    {
+        Chain::new(self, Distinct::by(key_fn))
+    }
+
+    /// see DistinctUntilChanged
+    fn distinct_until_changed(self) -> Chain<Self, DistinctUntilChanged<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error, fn(&<Self as Publisher<'a>>::Output) -> <Self as Publisher<'a>>::Output>>
+    where <Self as Publisher<'a>>::Output : PartialEq + Clone
+    {
+        Chain::new(self, DistinctUntilChanged::new())
+    }
+
+    /// see Materialize
+    fn materialize(self) -> Ticked<Self, Materialize<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>> {
+        Ticked::new(self, Materialize::new())
+    }
+
+    /// see Dematerialize
+    fn dematerialize<T>(self) -> Chain<Self, Dematerialize<'a, T, <Self as Publisher<'a>>::Error>>
+    where Self : Publisher<'a, Output=Notification<T, <Self as Publisher<'a>>::Error>>
+    {
+        Chain::new(self, Dematerialize::new())
+    }
+
+    /// see Skip
+    fn skip(self, n: usize) -> Chain<Self, Skip<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>> {
+        Chain::new(self, Skip::new(n))
+    }
+
+    /// see SkipWhile
+    fn skip_while<F>(self, f: F) -> Chain<Self, SkipWhile<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error, F>>
+    where F : Fn(&<Self as Publisher<'a>>::Output) -> bool
+    {
+        Chain::new(self, SkipWhile::new(f))
+    }
+
+    /// see TakeWhile
+    fn take_while<F>(self, f: F) -> Chain<Self, TakeWhile<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error, F>>
+    where F : Fn(&<Self as Publisher<'a>>::Output) -> bool
+    {
+        Chain::new(self, TakeWhile::new(f))
+    }
+
+    /// see TakeUntil
+    fn take_until<F>(self, f: F) -> Chain<Self, TakeUntil<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error, F>>
+    where F : Fn(&<Self as Publisher<'a>>::Output) -> bool
+    {
+        Chain::new(self, TakeUntil::new(f))
+    }
+
+    /// see FlatMap
+    fn flat_map<O, U, F>(self, f: F) -> Ticked<Self, FlatMap<'a, <Self as Publisher<'a>>::Output, O, U, <Self as Publisher<'a>>::Error, F>>
+    where U : IntoIterator<Item=O>,
+          F : Fn(<Self as Publisher<'a>>::Output) -> U
+    {
+        Ticked::new(self, FlatMap::new(f))
+    }
+
+    /// see ConcatMap
+    fn concat_map<P, F>(self, f: F) -> Ticked<Self, ConcatMap<'a, <Self as Publisher<'a>>::Output, P, <Self as Publisher<'a>>::Error, F>>
+    where P : Publisher<'a, Error=<Self as Publisher<'a>>::Error>,
+          F : Fn(<Self as Publisher<'a>>::Output) -> P,
+          <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        Ticked::new(self, ConcatMap::new(f))
+    }
+
+    /// see Scan
+    fn scan<V, O, F>(self, initial: V, f: F) -> Chain<Self, Scan<'a, <Self as Publisher<'a>>::Output, V, O, <Self as Publisher<'a>>::Error, F>>
+    where F : FnMut(&mut V, <Self as Publisher<'a>>::Output) -> Option<O>
+    {
+        Chain::new(self, Scan::new(initial, f))
+    }
+
+    /// see Fold
+    fn fold<V, F>(self, initial: V, f: F) -> Chain<Self, Fold<'a, <Self as Publisher<'a>>::Output, V, <Self as Publisher<'a>>::Error, F>>
+    where F : FnMut(&mut V, <Self as Publisher<'a>>::Output)
+    {
+        Chain::new(self, Fold::new(initial, f))
+    }
+
+    /// see Buffer
+    fn buffer(self, max_count: usize, max_time: Duration) -> Ticked<Self, Buffer<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>> {
+        Ticked::new(self, Buffer::new(max_count, max_time))
+    }
+
+    /// see Buffer::with_count
+    fn buffer_count(self, max_count: usize) -> Ticked<Self, Buffer<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>> {
+        Ticked::new(self, Buffer::with_count(max_count))
+    }
+
+    /// see Buffer::with_time
+    fn buffer_time(self, max_time: Duration) -> Ticked<Self, Buffer<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>> {
+        Ticked::new(self, Buffer::with_time(max_time))
+    }
+
+    /// see Window
+    fn window(self, kind: WindowKind) -> Ticked<Self, Window<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>>
+    where <Self as Publisher<'a>>::Output : Clone
+    {
+        Ticked::new(self, Window::new(kind))
+    }
+
+    /// see Window::live
+    fn live_window(self, kind: WindowKind) -> Ticked<Self, Window<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error, RealClock, LivePane<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>>>
+    where <Self as Publisher<'a>>::Output : Clone,
+          <Self as Publisher<'a>>::Error : Clone
+    {
+        Ticked::new(self, Window::live(kind))
+    }
+
+    /// see GroupBy
+    fn group_by<K, F>(self, key_fn: F) -> Ticked<Self, GroupBy<'a, <Self as Publisher<'a>>::Output, K, <Self as Publisher<'a>>::Error, F>>
+    where K : Hash + Eq + Clone,
+          F : Fn(&<Self as Publisher<'a>>::Output) -> K,
+          <Self as Publisher<'a>>::Error : Clone
+    {
+        Ticked::new(self, GroupBy::new(key_fn))
+    }
+
+    /// see GroupBy::expiring
+    fn group_by_expiring<K, F>(self, key_fn: F, idle: Duration) -> Ticked<Self, GroupBy<'a, <Self as Publisher<'a>>::Output, K, <Self as Publisher<'a>>::Error, F>>
+    where K : Hash + Eq + Clone,
+          F : Fn(&<Self as Publisher<'a>>::Output) -> K,
+          <Self as Publisher<'a>>::Error : Clone
+    {
+        Ticked::new(self, GroupBy::expiring(key_fn, idle))
+    }
+
+    /// see Zip
+    fn zip<B>(self, other: B) -> Zip<'a, Self, B, <Self as Publisher<'a>>::Error>
+    where B : Publisher<'a, Error=<Self as Publisher<'a>>::Error>,
+          <Self as Publisher<'a>>::Output : 'a, <B as Publisher<'a>>::Output : 'a,
+          <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        Zip::new(self, other)
+    }
+
+    /// see CombineLatest
+    fn combine_latest<B>(self, other: B) -> CombineLatest<'a, Self, B, <Self as Publisher<'a>>::Error>
+    where B : Publisher<'a, Error=<Self as Publisher<'a>>::Error>,
+          <Self as Publisher<'a>>::Output : Clone + 'a, <B as Publisher<'a>>::Output : Clone + 'a,
+          <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        CombineLatest::new(self, other)
+    }
+
+    /// see Merge
+    fn merge<B>(self, other: B) -> Merge<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>
+    where B : Publisher<'a, Output=<Self as Publisher<'a>>::Output, Error=<Self as Publisher<'a>>::Error> + 'a,
+          Self : 'a,
+          <Self as Publisher<'a>>::Output : 'a,
+          <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        let mut m = Merge::new();
+        m.add(self);
+        m.add(other);
+        m
+    }
+
+    /// see ThrottleFirst
+    fn throttle_first(self, window: Duration) -> Chain<Self, ThrottleFirst<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>> {
+        Chain::new(self, ThrottleFirst::new(window))
+    }
+
+    /// see ThrottleLast
+    fn throttle_last(self, window: Duration) -> ThrottleLast<'a, Self>
+    where <Self as Publisher<'a>>::Output : 'a, <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        ThrottleLast::new(self, window)
+    }
+
+    /// see Debounce
+    fn debounce(self, quiet: Duration) -> Debounce<'a, Self>
+    where <Self as Publisher<'a>>::Output : 'a, <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        Debounce::new(self, quiet)
+    }
+
+    /// see Sample
+    fn sample(self, period: Duration) -> Sample<'a, Self>
+    where <Self as Publisher<'a>>::Output : 'a, <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        Sample::new(self, period)
+    }
+
+    /// see RateLimit::blocking
+    fn rate_limit(self, per_second: u64, burst: usize) -> Chain<Self, RateLimit<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>> {
+        Chain::new(self, RateLimit::blocking(per_second, burst))
+    }
+
+    /// see RateLimit::dropping
+    fn rate_limit_dropping(self, per_second: u64, burst: usize) -> Chain<Self, RateLimit<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>> {
+        Chain::new(self, RateLimit::dropping(per_second, burst))
+    }
+
+    /// see RateLimit::erroring
+    fn rate_limit_erroring(self, per_second: u64, burst: usize) -> Chain<Self, RateLimit<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error>>
+    where <Self as Publisher<'a>>::Error : FromError<RateLimited>
+    {
+        Chain::new(self, RateLimit::erroring(per_second, burst))
+    }
+
+    /// see OnErrorReturn
+    fn on_error_return<F>(self, f: F) -> Chain<Self, OnErrorReturn<'a, <Self as Publisher<'a>>::Output, <Self as Publisher<'a>>::Error, F>>
+    where F : Fn(<Self as Publisher<'a>>::Error) -> <Self as Publisher<'a>>::Output
+    {
+        Chain::new(self, OnErrorReturn::new(f))
+    }
+
+    /// see Retry::new, this publisher is the first attempt,
+    /// and factory makes one for each retry
+    fn retry<F>(self, factory: F, times: usize) -> Retry<'a, Self, F>
+    where F : Fn() -> Self,
+          <Self as Publisher<'a>>::Output : 'a, <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        Retry::new(factory, times).starting_with(self)
+    }
+
+    /// see Retry::when, this publisher is the first attempt,
+    /// and factory makes one for each retry
+    fn retry_when<F, B>(self, factory: F, backoff: B) -> Retry<'a, Self, F>
+    where F : Fn() -> Self,
+          B : FnMut(usize, &<Self as Publisher<'a>>::Error) -> Option<Duration> + 'a,
+          <Self as Publisher<'a>>::Output : 'a, <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        Retry::when(factory, backoff).starting_with(self)
+    }
+
+    /// see OnErrorResume
+    fn on_error_resume<Q, F>(self, fallback: F) -> OnErrorResume<'a, Self, Q, F>
+    where Q : Publisher<'a, Output=<Self as Publisher<'a>>::Output, Error=<Self as Publisher<'a>>::Error>,
+          F : FnOnce(<Self as Publisher<'a>>::Error) -> Q,
+          <Self as Publisher<'a>>::Output : 'a, <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        OnErrorResume::new(self, fallback)
+    }
 
-    fn map<'a, O, F>(mut self, f: F) -> PublisherWrapper<'a, Self, O>
-    where F : Fn(Self::Output) -> O
+    /// see Timeout::new
+    fn timeout(self, timeout: Duration) -> Timeout<'a, Self, Self>
+    where <Self as Publisher<'a>>::Output : 'a,
+          <Self as Publisher<'a>>::Error : FromError<TimedOut> + Debug + 'a
     {
-        let mut m : Box<Processor<'a, Input=Self::Output, Output=O> + 'a> = Box::new(Map::new(f));
-        let t = Arc::new(RefCell::new(m));
-        self.subscribe(*t.borrow_mut());
-        PublisherWrapper { head: self, tail: t.clone()  }
+        Timeout::new(self, timeout)
+    }
+
+    /// see Timeout::or_else
+    fn timeout_or_else<Q>(self, timeout: Duration, fallback: Q) -> Timeout<'a, Self, Q>
+    where Q : Publisher<'a, Output=<Self as Publisher<'a>>::Output, Error=<Self as Publisher<'a>>::Error>,
+          <Self as Publisher<'a>>::Output : 'a, <Self as Publisher<'a>>::Error : Debug + 'a
+    {
+        Timeout::or_else(self, timeout, fallback)
     }
 }
 
 impl<'a, P> PublisherExt<'a> for P where P : Publisher<'a> {}
 
-pub struct PublisherWrapper<'a, P, O>
-where P : Publisher<'a> + 'a
-{
-    head: P,
-    tail: Arc<RefCell<Box<Processor<'a> + 'a>>>
-}
+#[cfg(test)]
+mod test {
+    use publisher::{IterPublisher, Coupler, Merge, PublisherExt, slot_with_capacity};
+    use processor::WindowKind;
+    use subscriber::Collect;
+    use reactive::{Publisher, Notification};
+    use std::sync::mpsc::channel;
+    use std::cell::Cell;
+    use std::time::Duration;
+
+    #[test]
+    fn chained_builder() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0us, 10))
+                .map(|i| i * 2)
+                .enumerate()
+                .take(3);
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![(0, 0), (2, 1), (4, 2)]);
+    }
+
+    #[test]
+    fn take_zero() {
+        let mut v: Box<Vec<u32>> = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 10))
+                .take(0);
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn take_zero_on_a_quiet_upstream() {
+        let (tx, rx) = channel::<u32>();
+        let mut v: Box<Vec<u32>> = Box::new(Vec::new());
+        let more = {
+            let mut chain = Coupler::<_, ()>::new(rx).take(0);
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            // nothing is ever sent, yet it is over without waiting for an item
+            chain.next()
+        };
+        assert!(!more);
+        assert!(v.is_empty());
+        drop(tx);
+    }
+
+    #[test]
+    fn unzip_on_demand() {
+        let mut all = Box::new(Vec::new());
+        let mut limited = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(vec![(0u32, 1u32), (2, 3)].into_iter())
+                .unzip();
+            chain.subscribe(Box::new(Collect::new(&mut all)));
+            chain.run();
+
+            // the second tuple only has demand for its first item,
+            // so the other is held back rather than pushed on regardless
+            let mut chain = IterPublisher::<_, _, ()>::new(vec![(0u32, 1u32), (2, 3)].into_iter())
+                .unzip();
+            chain.subscribe(Box::new(Collect::with_demand(&mut limited, 3)));
+            for _ in range(0, 10) { chain.next(); }
+        }
+        assert_eq!(*all, vec![0, 1, 2, 3]);
+        assert_eq!(*limited, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn filters() {
+        let mut evens = Box::new(Vec::new());
+        let mut halves = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 10))
+                .filter(|i: &u32| *i % 2 == 0);
+            chain.subscribe(Box::new(Collect::new(&mut evens)));
+            chain.run();
+
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 10))
+                .filter_map(|i: u32| if i % 2 == 0 { Some(i / 2) } else { None });
+            chain.subscribe(Box::new(Collect::new(&mut halves)));
+            chain.run();
+        }
+        assert_eq!(*evens, vec![0, 2, 4, 6, 8]);
+        assert_eq!(*halves, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn filter_on_demand() {
+        // what is filtered out does not use up any demand
+        let mut odds = Box::new(Vec::new());
+        let mut halves = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 10))
+                .filter(|i: &u32| *i % 2 == 1);
+            chain.subscribe(Box::new(Collect::with_demand(&mut odds, 2)));
+            for _ in range(0, 20) { chain.next(); }
+
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 10))
+                .filter_map(|i: u32| if i % 2 == 0 { Some(i / 2) } else { None });
+            chain.subscribe(Box::new(Collect::with_demand(&mut halves, 1)));
+            for _ in range(0, 20) { chain.next(); }
+        }
+        assert_eq!(*odds, vec![1, 3]);
+        assert_eq!(*halves, vec![0]);
+    }
+
+    #[test]
+    fn skips() {
+        let mut skipped = Box::new(Vec::new());
+        let mut skipped_while = Box::new(Vec::new());
+        let mut limited = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 6))
+                .skip(3);
+            chain.subscribe(Box::new(Collect::new(&mut skipped)));
+            chain.run();
+
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 6))
+                .skip_while(|i: &u32| *i < 4);
+            chain.subscribe(Box::new(Collect::new(&mut skipped_while)));
+            chain.run();
+
+            // skipped items do not use up any demand either
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 6))
+                .skip(2);
+            chain.subscribe(Box::new(Collect::with_demand(&mut limited, 1)));
+            for _ in range(0, 20) { chain.next(); }
+        }
+        assert_eq!(*skipped, vec![3, 4, 5]);
+        assert_eq!(*skipped_while, vec![4, 5]);
+        assert_eq!(*limited, vec![2]);
+    }
+
+    #[test]
+    fn takes() {
+        let mut taken_while = Box::new(Vec::new());
+        let mut taken_until = Box::new(Vec::new());
+        let mut limited = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 10))
+                .take_while(|i: &u32| *i < 3);
+            chain.subscribe(Box::new(Collect::new(&mut taken_while)));
+            chain.run();
+
+            // the item which ends it is passed on as well
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 10))
+                .take_until(|i: &u32| *i == 2);
+            chain.subscribe(Box::new(Collect::new(&mut taken_until)));
+            chain.run();
+
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 10))
+                .take_while(|i: &u32| *i < 5);
+            chain.subscribe(Box::new(Collect::with_demand(&mut limited, 2)));
+            for _ in range(0, 20) { chain.next(); }
+        }
+        assert_eq!(*taken_while, vec![0, 1, 2]);
+        assert_eq!(*taken_until, vec![0, 1, 2]);
+        assert_eq!(*limited, vec![0, 1]);
+    }
+
+    #[test]
+    fn flat_map_on_demand() {
+        let mut all = Box::new(Vec::new());
+        let mut limited = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 3))
+                .flat_map(|i: u32| vec![i, i]);
+            chain.subscribe(Box::new(Collect::new(&mut all)));
+            chain.run();
+
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 3))
+                .flat_map(|i: u32| vec![i, i]);
+            chain.subscribe(Box::new(Collect::with_demand(&mut limited, 3)));
+            for _ in range(0, 20) { chain.next(); }
+        }
+        assert_eq!(*all, vec![0, 0, 1, 1, 2, 2]);
+        assert_eq!(*limited, vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn concat_map_on_demand() {
+        let mut all = Box::new(Vec::new());
+        let mut limited = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(1u32, 4))
+                .concat_map(|i: u32| IterPublisher::new(vec![i, i].into_iter()));
+            chain.subscribe(Box::new(Collect::new(&mut all)));
+            chain.run();
+
+            let mut chain = IterPublisher::<_, _, ()>::new(range(1u32, 4))
+                .concat_map(|i: u32| IterPublisher::new(vec![i, i].into_iter()));
+            chain.subscribe(Box::new(Collect::with_demand(&mut limited, 3)));
+            for _ in range(0, 20) { chain.next(); }
+        }
+        assert_eq!(*all, vec![1, 1, 2, 2, 3, 3]);
+        assert_eq!(*limited, vec![1, 1, 2]);
+    }
+
+    #[test]
+    fn left_over_at_completion_waits_for_demand() {
+        let mut flat = Box::new(Vec::new());
+        let mut concat = Box::new(Vec::new());
+        let (flat_more, concat_more) = {
+            // the upstream's only item is in and it has completed
+            // well before the subscriber has asked for all it expands to
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 1))
+                .flat_map(|i: u32| vec![i, i, i, i]);
+            chain.subscribe(Box::new(Collect::with_demand(&mut flat, 2)));
+            for _ in range(0, 10) { chain.next(); }
+            let flat_more = chain.next();
+
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 1))
+                .concat_map(|i: u32| IterPublisher::new(vec![i, i, i, i].into_iter()));
+            chain.subscribe(Box::new(Collect::with_demand(&mut concat, 2)));
+            for _ in range(0, 10) { chain.next(); }
+            (flat_more, chain.next())
+        };
+        assert_eq!(*flat, vec![0, 0]);
+        assert_eq!(*concat, vec![0, 0]);
+        // and neither is complete, the rest is still owed
+        assert!(flat_more);
+        assert!(concat_more);
+    }
 
-*/
+    #[test]
+    fn concat_map_inner_error_ends_stream() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(1u32, 4))
+                .concat_map(|i: u32| {
+                    IterPublisher::new(vec![Notification::Next(i), Notification::Error(())].into_iter())
+                        .dematerialize()
+                })
+                .materialize();
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![Notification::Next(1), Notification::Error(()), Notification::Complete]);
+    }
+
+    #[test]
+    fn scan_keeps_state() {
+        let mut all = Box::new(Vec::new());
+        let mut limited = Box::new(Vec::new());
+        {
+            // the state is a Vec, which Reduce could not hold
+            let mut chain = IterPublisher::<_, _, ()>::new(range(1u32, 5))
+                .scan(Vec::new(), |seen: &mut Vec<u32>, i: u32| { seen.push(i); Some(seen.len()) });
+            chain.subscribe(Box::new(Collect::new(&mut all)));
+            chain.run();
+
+            // what is held back does not use up any demand
+            let mut chain = IterPublisher::<_, _, ()>::new(range(1u32, 10))
+                .scan(0u32, |total: &mut u32, i: u32| {
+                    *total += i;
+                    if i % 2 == 0 { Some(*total) } else { None }
+                });
+            chain.subscribe(Box::new(Collect::with_demand(&mut limited, 2)));
+            for _ in range(0, 20) { chain.next(); }
+        }
+        assert_eq!(*all, vec![1, 2, 3, 4]);
+        assert_eq!(*limited, vec![3, 10]);
+    }
+
+    #[test]
+    fn fold_passes_final_state() {
+        let mut all = Box::new(Vec::new());
+        let mut limited = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(1u32, 5))
+                .fold(Vec::new(), |acc: &mut Vec<u32>, i: u32| acc.push(i * 2));
+            chain.subscribe(Box::new(Collect::new(&mut all)));
+            chain.run();
+
+            // the items folded in do not use up the one unit asked for
+            let mut chain = IterPublisher::<_, _, ()>::new(range(1u32, 5))
+                .fold(0u32, |acc: &mut u32, i: u32| *acc += i);
+            chain.subscribe(Box::new(Collect::with_demand(&mut limited, 1)));
+            for _ in range(0, 20) { chain.next(); }
+        }
+        assert_eq!(*all, vec![vec![2, 4, 6, 8]]);
+        assert_eq!(*limited, vec![10]);
+    }
+
+    #[test]
+    fn distinct_and_until_changed() {
+        let mut all = Box::new(Vec::new());
+        let mut runs = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(vec![1u32, 1, 2, 1, 3, 3].into_iter())
+                .distinct();
+            chain.subscribe(Box::new(Collect::new(&mut all)));
+            chain.run();
+
+            let mut chain = IterPublisher::<_, _, ()>::new(vec![1u32, 1, 2, 1, 3, 3].into_iter())
+                .distinct_until_changed();
+            chain.subscribe(Box::new(Collect::new(&mut runs)));
+            chain.run();
+        }
+        assert_eq!(*all, vec![1, 2, 3]);
+        assert_eq!(*runs, vec![1, 2, 1, 3]);
+    }
+
+    #[test]
+    fn materialize_round_trip() {
+        let mut signals = Box::new(Vec::new());
+        let mut items = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 2))
+                .materialize();
+            chain.subscribe(Box::new(Collect::new(&mut signals)));
+            chain.run();
+
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 2))
+                .materialize()
+                .dematerialize();
+            chain.subscribe(Box::new(Collect::new(&mut items)));
+            chain.run();
+        }
+        assert_eq!(*signals, vec![Notification::Next(0), Notification::Next(1), Notification::Complete]);
+        assert_eq!(*items, vec![0, 1]);
+    }
+
+    #[test]
+    fn materialize_holds_the_end_until_asked() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 2))
+                .materialize();
+            chain.subscribe(Box::new(Collect::with_demand(&mut v, 2)));
+            for _ in range(0, 5) {
+                assert!(chain.try_next());
+            }
+        }
+        assert_eq!(*v, vec![Notification::Next(0), Notification::Next(1)]);
+    }
+
+    #[test]
+    fn windows() {
+        let mut tumbling = Box::new(Vec::new());
+        let mut sliding = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 5))
+                .window(WindowKind::Tumbling(2));
+            chain.subscribe(Box::new(Collect::new(&mut tumbling)));
+            chain.run();
+
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 5))
+                .window(WindowKind::Sliding(3, 2));
+            chain.subscribe(Box::new(Collect::new(&mut sliding)));
+            chain.run();
+        }
+        assert_eq!(*tumbling, vec![vec![0, 1], vec![2, 3], vec![4]]);
+        assert_eq!(*sliding, vec![vec![0, 1, 2], vec![2, 3, 4], vec![4]]);
+    }
+
+    #[test]
+    fn window_with_a_negative_span() {
+        let mut v = Box::new(Vec::new());
+        {
+            // counts as no time at all, so each window closes as soon as it opens
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 3))
+                .window(WindowKind::Timed(Duration::milliseconds(-5)));
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![vec![0], vec![1], vec![2]]);
+    }
+
+    #[test]
+    #[should_fail]
+    fn window_of_zero() {
+        IterPublisher::<_, _, ()>::new(range(0u32, 5)).window(WindowKind::Sliding(3, 0));
+    }
+
+    #[test]
+    fn buffer_count_on_demand() {
+        let mut all = Box::new(Vec::new());
+        let mut limited = Box::new(Vec::new());
+        let more = {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 5))
+                .buffer_count(2);
+            chain.subscribe(Box::new(Collect::new(&mut all)));
+            chain.run();
+
+            // the partial batch left at completion waits for demand,
+            // and the stream is not over until it has gone
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 5))
+                .buffer_count(2);
+            chain.subscribe(Box::new(Collect::with_demand(&mut limited, 2)));
+            for _ in range(0, 10) { chain.next(); }
+            chain.next()
+        };
+        assert_eq!(*all, vec![vec![0, 1], vec![2, 3], vec![4]]);
+        assert_eq!(*limited, vec![vec![0, 1], vec![2, 3]]);
+        assert!(more);
+    }
+
+    #[test]
+    fn buffer_with_a_negative_time() {
+        let mut v = Box::new(Vec::new());
+        {
+            // counts as no time at all, so each batch is due as soon as it starts
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 3))
+                .buffer_time(Duration::milliseconds(-5));
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![vec![0], vec![1], vec![2]]);
+    }
+
+    #[test]
+    #[should_fail]
+    fn buffer_of_zero() {
+        IterPublisher::<_, _, ()>::new(range(0u32, 5)).buffer_count(0);
+    }
+
+    #[test]
+    fn slot_asks_for_its_capacity() {
+        let mut p = IterPublisher::<_, _, ()>::new(range(0u32, 10));
+        let slot = slot_with_capacity(&mut p, 2);
+        for _ in range(0, 5) { p.try_next(); }
+        assert_eq!(slot.borrow().len(), 2);
+        assert_eq!(slot.borrow_mut().pop(), Some(0));
+        for _ in range(0, 5) { p.try_next(); }
+        assert_eq!(slot.borrow().len(), 2);
+    }
+
+    #[test]
+    fn zips() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 1000))
+                .zip(IterPublisher::new(vec!['a', 'b', 'c'].into_iter()));
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![(0, 'a'), (1, 'b'), (2, 'c')]);
+    }
+
+    #[test]
+    fn zip_does_not_wait_once_a_side_runs_dry() {
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            // the sender stays alive, so a blocking pull on the right would never return
+            tx.send('a').unwrap();
+            tx.send('b').unwrap();
+            tx.send('c').unwrap();
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 2))
+                .zip(Coupler::new(rx));
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![(0, 'a'), (1, 'b')]);
+        drop(tx);
+    }
+
+    #[test]
+    fn zip_on_demand() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 10))
+                .zip(IterPublisher::new(range(10u32, 20)));
+            chain.subscribe(Box::new(Collect::with_demand(&mut v, 2)));
+            for _ in range(0, 20) { chain.try_next(); }
+        }
+        assert_eq!(*v, vec![(0, 10), (1, 11)]);
+    }
+
+    #[test]
+    fn combines_the_latest_of_each() {
+        let (ltx, lrx) = channel();
+        let (rtx, rrx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = Coupler::<u32, ()>::new(lrx)
+                .combine_latest(Coupler::new(rrx));
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            // nothing goes out until both sides have something
+            ltx.send(1).unwrap();
+            chain.try_next();
+            rtx.send(10).unwrap();
+            chain.try_next();
+            ltx.send(2).unwrap();
+            chain.try_next();
+            rtx.send(20).unwrap();
+            chain.try_next();
+            drop(ltx);
+            drop(rtx);
+            while chain.try_next() {}
+        }
+        assert_eq!(*v, vec![(1, 10), (2, 10), (2, 20)]);
+    }
+
+    #[test]
+    fn combine_latest_completes_when_a_side_ends_empty() {
+        let (tx, rx) = channel::<u32>();
+        let mut v: Box<Vec<(u32, u32)>> = Box::new(Vec::new());
+        let steps = {
+            // the left side stays open, but nothing could ever be combined with it
+            let mut chain = Coupler::<u32, ()>::new(rx)
+                .combine_latest(IterPublisher::new(Vec::new().into_iter()));
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            let mut steps = 1;
+            while chain.try_next() { steps += 1; }
+            steps
+        };
+        assert_eq!(steps, 1);
+        assert!(v.is_empty());
+        drop(tx);
+    }
+
+    #[test]
+    fn combine_latest_passes_on_an_error() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::new(vec![Notification::Next(1u32), Notification::Error(())].into_iter())
+                .dematerialize()
+                .combine_latest(IterPublisher::new(range(10u32, 13)))
+                .materialize();
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![Notification::Next((1, 10)), Notification::Error(())]);
+    }
+
+    #[test]
+    fn merges_taking_turns() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 3))
+                .merge(IterPublisher::new(range(10u32, 13)));
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![0, 10, 1, 11, 2, 12]);
+    }
+
+    #[test]
+    fn merge_on_demand() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::<_, _, ()>::new(range(0u32, 3))
+                .merge(IterPublisher::new(range(10u32, 13)));
+            chain.subscribe(Box::new(Collect::with_demand(&mut v, 3)));
+            for _ in range(0, 20) { chain.try_next(); }
+        }
+        assert_eq!(*v, vec![0, 10, 1]);
+    }
+
+    #[test]
+    fn merge_holds_an_error_back() {
+        let mut held = Box::new(Vec::new());
+        let mut fast = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::new(vec![Notification::Next(1u32), Notification::Error(())].into_iter())
+                .dematerialize()
+                .merge(IterPublisher::new(range(10u32, 13)))
+                .materialize();
+            chain.subscribe(Box::new(Collect::new(&mut held)));
+            chain.run();
+
+            let mut m = Merge::fail_fast();
+            m.add(IterPublisher::new(vec![Notification::Next(1u32), Notification::Error(())].into_iter()).dematerialize());
+            m.add(IterPublisher::new(range(10u32, 13)));
+            let mut chain = m.materialize();
+            chain.subscribe(Box::new(Collect::new(&mut fast)));
+            chain.run();
+        }
+        assert_eq!(*held, vec![Notification::Next(1), Notification::Next(10), Notification::Next(11),
+                               Notification::Next(12), Notification::Error(()), Notification::Complete]);
+        assert_eq!(*fast, vec![Notification::Next(1), Notification::Next(10), Notification::Error(()), Notification::Complete]);
+    }
+
+    #[test]
+    fn retries_then_gives_up() {
+        let attempts = Cell::new(0u32);
+        let mut v = Box::new(Vec::new());
+        {
+            let make = || {
+                attempts.set(attempts.get() + 1);
+                IterPublisher::new(vec![Notification::Next(attempts.get()), Notification::Error(())].into_iter())
+                    .dematerialize()
+            };
+            let first = make();
+            let mut chain = first.retry(make, 2).materialize();
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![Notification::Next(1), Notification::Next(2), Notification::Next(3),
+                            Notification::Error(()), Notification::Complete]);
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[test]
+    fn retries_until_it_gets_through() {
+        let attempts = Cell::new(0u32);
+        let mut v = Box::new(Vec::new());
+        {
+            let make = || {
+                attempts.set(attempts.get() + 1);
+                let n = attempts.get();
+                let mut signals = vec![Notification::Next(n)];
+                if n < 3 { signals.push(Notification::Error(())); }
+                IterPublisher::new(signals.into_iter()).dematerialize()
+            };
+            let first = make();
+            let mut chain = first.retry(make, 5).materialize();
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![Notification::Next(1), Notification::Next(2), Notification::Next(3), Notification::Complete]);
+    }
+
+    #[test]
+    fn resumes_on_the_fallback() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::new(vec![Notification::Next(1u32), Notification::Error(())].into_iter())
+                .dematerialize()
+                .on_error_resume(|_| IterPublisher::new(range(10u32, 12)))
+                .materialize();
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![Notification::Next(1), Notification::Next(10), Notification::Next(11), Notification::Complete]);
+    }
+
+    #[test]
+    fn returns_an_item_for_the_error() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = IterPublisher::new(vec![Notification::Next(1u32), Notification::Error(())].into_iter())
+                .dematerialize()
+                .on_error_return(|_| 99)
+                .materialize();
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![Notification::Next(1), Notification::Next(99), Notification::Complete]);
+    }
+}
@@ -3,11 +3,23 @@
 // This software may be modified and distributed under the terms
 // of the MIT license.  See the LICENSE file for details.
 
-use reactive::{Publisher, Subscriber};
+use reactive::{Publisher, Subscriber, Subscription, UNBOUNDED};
+use strategy::{InputStrategy, Inlet, RoundRobinInput};
+use scheduler::{Clock, RealClock, Tick, idle, clamped_ns};
+use processorimpl::{Relay, RelayState};
+use error::TimedOut;
 
 use quickcheck::{Arbitrary, Gen, StdGen};
 
 use std::sync::mpsc::{Sender, Receiver, TryRecvError};
+use std::thread::Thread;
+use std::collections::RingBuf;
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::fmt::Debug;
+use std::time::Duration;
+use std::error::FromError;
 use std::rand::Rng;
 
 use rand::isaac::Isaac64Rng as IRng;
@@ -30,35 +42,51 @@ impl Rng for TestIncGen {
     }
 }
 
-pub struct RndGen<'a, 'b, O> where O : Arbitrary {
-    subscriber: Option<Box<Subscriber<Input=O> + 'a>>,
+pub struct RndGen<'a, 'b, O, E> where O : Arbitrary {
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Subscription,
     gen: StdGen<TestIncGen>
 }
 
-impl<'a, 'b, O> RndGen<'a, 'b, O> where O : Arbitrary {
-    pub fn new() -> RndGen<'a, 'b, O>
+impl<'a, 'b, O, E> RndGen<'a, 'b, O, E> where O : Arbitrary {
+    pub fn new() -> RndGen<'a, 'b, O, E>
     {
         RndGen {
             subscriber: None,
+            subscription: Subscription::new(0),
             gen: StdGen::new(TestIncGen { current: 0 }, 1_000_000)
         }
     }
 
+    /// Drops the subscriber, and with it the rest of the chain
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        false
+    }
 }
 
-impl<'a,'b, O> Publisher<'a> for RndGen<'a, 'b, O> where  O : Arbitrary {
+impl<'a,'b, O, E> Publisher<'a> for RndGen<'a, 'b, O, E> where  O : Arbitrary {
     type Output = O;
-    fn subscribe(&mut self, s: Box<Subscriber<Input=O> + 'a>) {
-        let s: Box<Subscriber<Input=O>+'a> = s;
+    type Error = E;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
+        self.subscription = Subscription::new(0);
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
     }
 
     fn try_next(&mut self) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) => s.on_next(Arbitrary::arbitrary(&mut self.gen)),
+        if self.subscription.is_cancelled() { return self.release() }
+        let more = match self.subscriber.as_mut() {
+            Some(s) => {
+                if !self.subscription.take() { return true }
+                s.on_next(Arbitrary::arbitrary(&mut self.gen))
+            },
             None => {error!("My subscriber went away");false}
-        }
+        };
+        if !more { self.release(); }
+        more
     }
 }
 
@@ -67,141 +95,2353 @@ impl<'a,'b, O> Publisher<'a> for RndGen<'a, 'b, O> where  O : Arbitrary {
 // Iterator Publisher
 //
 
-pub struct IterPublisher<'a, 'b, 'c, O, Iter>
+pub struct IterPublisher<'a, 'b, 'c, O, Iter, E>
 where Iter: Iterator + 'b , O : 'c
 {
-    iter: Iter,
-    subscriber: Option<Box<Subscriber<Input=O> + 'a>>
+    iter: Peekable<Iter>,
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Subscription
 }
 
-impl<'a, 'b, 'c, O, Iter> IterPublisher<'a, 'b, 'c, O, Iter>
+impl<'a, 'b, 'c, O, Iter, E> IterPublisher<'a, 'b, 'c, O, Iter, E>
 where Iter: Iterator<Item=O> + 'b ,
       O : 'c
 {
-    pub fn new(iter: Iter) -> IterPublisher<'a, 'b, 'c, O, Iter>
+    pub fn new(iter: Iter) -> IterPublisher<'a, 'b, 'c, O, Iter, E>
     {
         IterPublisher {
-            iter: iter,
-            subscriber: None
+            iter: iter.peekable(),
+            subscriber: None,
+            subscription: Subscription::new(0)
         }
     }
 
-
+    /// Drops the subscriber, and with it the rest of the chain
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        false
+    }
 }
 
-impl<'a, 'b, 'c, O, Iter> Publisher<'a> for IterPublisher<'a, 'b, 'c, O, Iter>
+impl<'a, 'b, 'c, O, Iter, E> Publisher<'a> for IterPublisher<'a, 'b, 'c, O, Iter, E>
 where Iter: Iterator<Item=O> + 'b,
       O : 'c
 {
 
     type Output = O;
-    fn subscribe(&mut self, s: Box<Subscriber<Input=O> + 'a>) {
-        let s: Box<Subscriber<Input=O>+'a> = s;
+
+    type Error = E;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
+        self.subscription = Subscription::new(0);
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
     }
 
     fn next(&mut self) -> bool {
-        match self.subscriber.as_mut() {
+        if self.subscription.is_cancelled() { return self.release() }
+        let more = match self.subscriber.as_mut() {
             Some(s) => {
-                match self.iter.next() {
-                    Some(x) => s.on_next(x),
-                    None => { s.on_complete(false); false }
+                // the end of the iterator is passed on whether it has been asked for or not
+                if self.iter.peek().is_none() {
+                    s.on_complete(false);
+                    false
+                } else if self.subscription.take() {
+                    s.on_next(self.iter.next().unwrap())
+                } else {
+                    self.subscription.wait();
+                    true
                 }
             },
             None => {error!("My subscriber went away");false}
+        };
+        if !more { self.release(); }
+        more
+    }
+
+    // An iterator is never waited on, so only the demand can hold us up
+    fn try_next(&mut self) -> bool {
+        if !self.subscription.is_cancelled() && self.subscription.demand() == 0 && self.iter.peek().is_some() {
+            return true
         }
+        self.next()
     }
 }
 
 //
 // Coupler
 //
-pub struct Coupler<'a, O> where O : Send {
-    data_rx: Receiver<O>,
-    subscriber: Option<Box<Subscriber<Input=O> + 'a>>
+pub struct Coupler<'a, O, E> where O : Send {
+    data_rx: Option<Receiver<O>>,
+    pending: Option<O>,
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Subscription
 }
 
 
-impl<'a, O> Coupler<'a, O> where O : Send {
-    pub fn new(rx: Receiver<O>) -> Coupler<'a, O> {
+impl<'a, O, E> Coupler<'a, O, E> where O : Send {
+    pub fn new(rx: Receiver<O>) -> Coupler<'a, O, E> {
         Coupler {
-            data_rx: rx,
+            data_rx: Some(rx),
+            pending: None,
             subscriber: None,
+            subscription: Subscription::new(0)
         }
     }
 
+    /// Drops the subscriber and closes the receiver, so that
+    /// the sending side learns that nobody is listening anymore
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        self.data_rx = None;
+        self.pending = None;
+        false
+    }
 }
 
 
-impl<'a, O> Publisher<'a> for Coupler<'a, O> where O : Send {
+impl<'a, O, E> Publisher<'a> for Coupler<'a, O, E> where O : Send {
 
     type Output = O;
-    fn subscribe(&mut self, s: Box<Subscriber<Input=O> + 'a>) {
-        let s: Box<Subscriber<Input=O>+'a> = s;
+
+    type Error = E;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
+        self.subscription = Subscription::new(0);
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
     }
 
+    // Only blocks on the queue once there is demand, without it the queue
+    // is still looked at, so that its end can be passed on
     fn next (&mut self) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) => match self.data_rx.recv() {
-                Ok(d) => s.on_next(d),
-                Err(..) => { info!("The other end of the coupler queue went away"); s.on_complete(false); false }
-            },
-            None => { error!("My subscriber went away"); false }
+        if self.subscription.is_cancelled() { return self.release() }
+        if self.pending.is_none() && self.subscription.demand() > 0 {
+            if let Some(Ok(d)) = self.data_rx.as_ref().map(|rx| rx.recv()) {
+                self.pending = Some(d);
+            }
         }
+        let more = self.try_next();
+        if more && self.subscription.demand() == 0 { self.subscription.wait(); }
+        more
     }
 
-    // Does not block
+    // Does not block. An item which arrives before it is asked for is
+    // held back, so the end of the queue behind it can be noticed
     fn try_next(&mut self) -> bool {
-        match self.subscriber.as_mut() {
-            Some(s) => match self.data_rx.try_recv() {
-                Ok(d) => s.on_next(d),
-                Err(TryRecvError::Empty) => true,
-                Err(TryRecvError::Disconnected) => { 
-                    info!("The other end of the coupler queue went away"); s.on_complete(false); false 
+        if self.subscription.is_cancelled() { return self.release() }
+        let more = match (self.subscriber.as_mut(), self.data_rx.as_ref()) {
+            (Some(s), Some(rx)) => {
+                let next = match self.pending.take() {
+                    Some(d) => Ok(d),
+                    None => rx.try_recv()
+                };
+                match next {
+                    Ok(d) => if self.subscription.take() { s.on_next(d) } else { self.pending = Some(d); true },
+                    Err(TryRecvError::Empty) => true,
+                    Err(TryRecvError::Disconnected) => {
+                        info!("The other end of the coupler queue went away"); s.on_complete(false); false
+                    }
                 }
             },
-            None => { error!("My subscriber went away"); false }
-        }
+            _ => { error!("My subscriber went away"); false }
+        };
+        if !more { self.release(); }
+        more
     }
 }
 
 //
 // Repeat
 //
-pub struct Repeat<'a, O> where O : Send + Clone {
+pub struct Repeat<'a, O, E> where O : Send + Clone {
     val: O,
-    subscriber: Option<Box<Subscriber<Input=O> + 'a>>
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Subscription
 }
 
 
-impl<'a, O> Repeat<'a, O> where O : Send + Clone {
-    pub fn new(o: O) -> Repeat<'a, O> {
+impl<'a, O, E> Repeat<'a, O, E> where O : Send + Clone {
+    pub fn new(o: O) -> Repeat<'a, O, E> {
         Repeat {
             val: o,
             subscriber: None,
+            subscription: Subscription::new(0)
         }
     }
 
+    /// Drops the subscriber, and with it the rest of the chain
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        false
+    }
 }
 
 
-impl<'a, O> Publisher<'a> for Repeat<'a, O> where O : Send + Clone {
+impl<'a, O, E> Publisher<'a> for Repeat<'a, O, E> where O : Send + Clone {
     type Output = O;
-    fn subscribe(&mut self, s: Box<Subscriber<Input=O> + 'a>) {
-        let s: Box<Subscriber<Input=O>+'a> = s;
+    type Error = E;
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        let s: Box<Subscriber<Input=O, Error=E>+'a> = s;
+        self.subscription = Subscription::new(0);
         self.subscriber = Some(s);
-        self.subscriber.as_mut().unwrap().on_subscribe(0);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
     }
 
     fn next (&mut self) -> bool{
-        match self.subscriber.as_mut() {
-            Some(s) => s.on_next(self.val.clone()),
+        if self.subscription.is_cancelled() { return self.release() }
+        let more = match self.subscriber.as_mut() {
+            Some(s) => {
+                if !self.subscription.take() { self.subscription.wait(); return true }
+                s.on_next(self.val.clone())
+            },
+            None => { error!("My subscriber went away"); false }
+        };
+        if !more { self.release(); }
+        more
+    }
+
+    // Does not block
+    fn try_next(&mut self) -> bool {
+        if !self.subscription.is_cancelled() && self.subscription.demand() == 0 { return true }
+        self.next()
+    }
+}
+
+//
+// Slot
+//
+
+/// Where a publisher owned by a combinator such as Zip leaves
+/// what it produces, until the combinator is ready for it.
+/// The publisher is asked for no more than capacity items up front,
+/// and for one more each time one is taken from the slot
+pub struct Slot<T, E> {
+    items: RingBuf<T>,
+    error: Option<E>,
+    done: bool,
+    capacity: usize,
+    subscription: Option<Subscription>
+}
+
+impl<T, E> Slot<T, E> {
+
+    /// A slot which asks for everything the publisher has
+    pub fn new() -> Slot<T, E> {
+        Slot::with_capacity(UNBOUNDED)
+    }
+
+    pub fn with_capacity(capacity: usize) -> Slot<T, E> {
+        Slot {
+            items: RingBuf::new(),
+            error: None,
+            done: false,
+            capacity: capacity,
+            subscription: None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        let t = self.items.pop_front();
+        if t.is_some() && self.capacity != UNBOUNDED {
+            if let Some(sub) = self.subscription.as_ref() { sub.request(1); }
+        }
+        t
+    }
+
+    pub fn take_error(&mut self) -> Option<E> {
+        self.error.take()
+    }
+
+    /// The publisher feeding this slot has nothing more to give
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// The publisher is done, and everything it gave has been taken
+    pub fn is_drained(&self) -> bool {
+        self.done && self.items.is_empty()
+    }
+
+    pub fn set_done(&mut self) {
+        self.done = true;
+    }
+
+    /// Tells the publisher feeding this slot to stop
+    pub fn cancel(&mut self) {
+        self.done = true;
+        if let Some(sub) = self.subscription.as_ref() { sub.cancel(); }
+    }
+}
+
+/// The subscriber which fills a Slot
+pub struct SlotSubscriber<T, E> {
+    slot: Rc<RefCell<Slot<T, E>>>
+}
+
+impl<T, E> SlotSubscriber<T, E> {
+    pub fn new(slot: Rc<RefCell<Slot<T, E>>>) -> SlotSubscriber<T, E> {
+        SlotSubscriber {
+            slot: slot
+        }
+    }
+}
+
+impl<T, E> Subscriber for SlotSubscriber<T, E> where E : Debug {
+    type Input = T;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        let mut slot = self.slot.borrow_mut();
+        sub.request(slot.capacity);
+        slot.subscription = Some(sub);
+    }
+
+    fn on_next(&mut self, t: T) -> bool {
+        self.slot.borrow_mut().items.push_back(t);
+        true
+    }
+
+    fn on_error(&mut self, err: E) {
+        let mut slot = self.slot.borrow_mut();
+        slot.error = Some(err);
+        slot.done = true;
+    }
+
+    fn on_complete(&mut self, _: bool) {
+        self.slot.borrow_mut().done = true;
+    }
+}
+
+/// Subscribes a new Slot to the publisher, asking it for everything
+pub fn slot_for<'a, P>(p: &mut P) -> Rc<RefCell<Slot<<P as Publisher<'a>>::Output, <P as Publisher<'a>>::Error>>>
+where P : Publisher<'a>,
+      <P as Publisher<'a>>::Output : 'a,
+      <P as Publisher<'a>>::Error : Debug + 'a
+{
+    slot_with_capacity(p, UNBOUNDED)
+}
+
+/// Subscribes a new Slot to the publisher, asking it for no more
+/// than capacity items ahead of those taken from the slot
+pub fn slot_with_capacity<'a, P>(p: &mut P, capacity: usize) -> Rc<RefCell<Slot<<P as Publisher<'a>>::Output, <P as Publisher<'a>>::Error>>>
+where P : Publisher<'a>,
+      <P as Publisher<'a>>::Output : 'a,
+      <P as Publisher<'a>>::Error : Debug + 'a
+{
+    let slot = Rc::new(RefCell::new(Slot::with_capacity(capacity)));
+    p.subscribe(Box::new(SlotSubscriber::new(slot.clone())));
+    slot
+}
+
+/// Pulls one item's worth from a publisher feeding a slot,
+/// noting in the slot when the publisher has stopped
+fn pull<'a, P>(p: &mut P, slot: &Rc<RefCell<Slot<<P as Publisher<'a>>::Output, <P as Publisher<'a>>::Error>>>, blocking: bool)
+where P : Publisher<'a>
+{
+    if slot.borrow().is_done() {
+        return
+    }
+    let more = if blocking { p.next() } else { p.try_next() };
+    if !more {
+        slot.borrow_mut().set_done();
+    }
+}
+
+//
+// Zip
+//
+
+/// Zip
+/// pairs up the items of two publishers in the order they arrive.
+/// Each side is only pulled on while fewer than capacity of its items
+/// are waiting for a partner. Completes once either side has completed
+/// and none of its items are left waiting, as no more pairs can be made
+pub struct Zip<'a, A, B, E>
+where A : Publisher<'a, Error=E>, B : Publisher<'a, Error=E>
+{
+    left: A,
+    right: B,
+    left_slot: Rc<RefCell<Slot<<A as Publisher<'a>>::Output, E>>>,
+    right_slot: Rc<RefCell<Slot<<B as Publisher<'a>>::Output, E>>>,
+    capacity: usize,
+    subscriber: Option<Box<Subscriber<Input=(<A as Publisher<'a>>::Output, <B as Publisher<'a>>::Output), Error=E> + 'a>>,
+    subscription: Subscription
+}
+
+impl<'a, A, B, E> Zip<'a, A, B, E>
+where A : Publisher<'a, Error=E>, B : Publisher<'a, Error=E>,
+      <A as Publisher<'a>>::Output : 'a, <B as Publisher<'a>>::Output : 'a, E : Debug + 'a
+{
+    pub fn new(left: A, right: B) -> Zip<'a, A, B, E> {
+        Zip::with_capacity(left, right, 128)
+    }
+
+    pub fn with_capacity(mut left: A, mut right: B, capacity: usize) -> Zip<'a, A, B, E> {
+        let ls = slot_with_capacity(&mut left, capacity);
+        let rs = slot_with_capacity(&mut right, capacity);
+        Zip {
+            left: left,
+            right: right,
+            left_slot: ls,
+            right_slot: rs,
+            capacity: capacity,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    /// Drops the subscriber and tells both sides to stop
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        self.left_slot.borrow_mut().cancel();
+        self.right_slot.borrow_mut().cancel();
+        false
+    }
+
+    fn step(&mut self, blocking: bool) -> bool {
+        if self.subscription.is_cancelled() { return self.release() }
+        if self.subscriber.is_none() { error!("My subscriber went away"); return false }
+
+        // when blocking, only wait on a side which has nothing to pair up,
+        // and never once the other side has run dry
+        if !self.is_finished() && self.left_slot.borrow().len() < self.capacity && (!blocking || self.left_slot.borrow().is_empty()) {
+            pull(&mut self.left, &self.left_slot, blocking);
+        }
+        if !self.is_finished() && self.right_slot.borrow().len() < self.capacity && (!blocking || self.right_slot.borrow().is_empty()) {
+            pull(&mut self.right, &self.right_slot, blocking);
+        }
+
+        let err = self.left_slot.borrow_mut().take_error().or_else(|| self.right_slot.borrow_mut().take_error());
+        if let Some(e) = err {
+            self.subscriber.as_mut().unwrap().on_error(e);
+            return self.release()
+        }
+
+        while !self.left_slot.borrow().is_empty() && !self.right_slot.borrow().is_empty() {
+            if !self.subscription.take() {
+                if blocking { Thread::yield_now(); }
+                return true
+            }
+            let pair = (self.left_slot.borrow_mut().pop().unwrap(), self.right_slot.borrow_mut().pop().unwrap());
+            if !self.subscriber.as_mut().unwrap().on_next(pair) {
+                return self.release()
+            }
+        }
+
+        if self.is_finished() {
+            self.subscriber.as_mut().unwrap().on_complete(false);
+            return self.release()
+        }
+        true
+    }
+
+    /// One side has ended with nothing left over to pair up, so no more pairs can be made
+    fn is_finished(&self) -> bool {
+        self.left_slot.borrow().is_drained() || self.right_slot.borrow().is_drained()
+    }
+}
+
+impl<'a, A, B, E> Publisher<'a> for Zip<'a, A, B, E>
+where A : Publisher<'a, Error=E>, B : Publisher<'a, Error=E>,
+      <A as Publisher<'a>>::Output : 'a, <B as Publisher<'a>>::Output : 'a, E : Debug + 'a
+{
+    type Output = (<A as Publisher<'a>>::Output, <B as Publisher<'a>>::Output);
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=(<A as Publisher<'a>>::Output, <B as Publisher<'a>>::Output), Error=E> + 'a>) {
+        self.subscription = Subscription::new(0);
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
+    }
+
+    fn next(&mut self) -> bool {
+        self.step(true)
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.step(false)
+    }
+}
+
+//
+// CombineLatest
+//
+
+/// CombineLatest
+/// remembers the latest item from each of two publishers, and whenever
+/// either produces an item, passes on a copy of both latest items.
+/// Nothing is passed on until both have produced something.
+/// Only the latest item matters, so each side is asked for one item at a
+/// time, and only pulled on again once that has been taken.
+/// Completes once both sides have completed, or as soon as one completes
+/// without ever having produced anything
+pub struct CombineLatest<'a, A, B, E>
+where A : Publisher<'a, Error=E>, B : Publisher<'a, Error=E>,
+      <A as Publisher<'a>>::Output : Clone, <B as Publisher<'a>>::Output : Clone
+{
+    left: A,
+    right: B,
+    left_slot: Rc<RefCell<Slot<<A as Publisher<'a>>::Output, E>>>,
+    right_slot: Rc<RefCell<Slot<<B as Publisher<'a>>::Output, E>>>,
+    latest_left: Option<<A as Publisher<'a>>::Output>,
+    latest_right: Option<<B as Publisher<'a>>::Output>,
+    left_turn: bool,
+    subscriber: Option<Box<Subscriber<Input=(<A as Publisher<'a>>::Output, <B as Publisher<'a>>::Output), Error=E> + 'a>>,
+    subscription: Subscription
+}
+
+impl<'a, A, B, E> CombineLatest<'a, A, B, E>
+where A : Publisher<'a, Error=E>, B : Publisher<'a, Error=E>,
+      <A as Publisher<'a>>::Output : Clone + 'a, <B as Publisher<'a>>::Output : Clone + 'a, E : Debug + 'a
+{
+    pub fn new(mut left: A, mut right: B) -> CombineLatest<'a, A, B, E> {
+        let ls = slot_with_capacity(&mut left, 1);
+        let rs = slot_with_capacity(&mut right, 1);
+        CombineLatest {
+            left: left,
+            right: right,
+            left_slot: ls,
+            right_slot: rs,
+            latest_left: None,
+            latest_right: None,
+            left_turn: true,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    /// Drops the subscriber and tells both sides to stop
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        self.left_slot.borrow_mut().cancel();
+        self.right_slot.borrow_mut().cancel();
+        false
+    }
+
+    fn emit(&mut self) -> bool {
+        match (self.latest_left.as_ref(), self.latest_right.as_ref()) {
+            (Some(l), Some(r)) => {
+                self.subscription.take();
+                self.subscriber.as_mut().unwrap().on_next((l.clone(), r.clone()))
+            },
+            _ => true
+        }
+    }
+
+    fn step(&mut self, blocking: bool) -> bool {
+        if self.subscription.is_cancelled() { return self.release() }
+        if self.subscriber.is_none() { error!("My subscriber went away"); return false }
+
+        if self.subscription.demand() > 0 {
+            // a blocking pull on one side could starve the other,
+            // so when blocking, take turns. A side whose item is
+            // still waiting to be taken is not asked for another
+            let (pull_left, pull_right) = (self.left_slot.borrow().is_empty(), self.right_slot.borrow().is_empty());
+            if pull_left && (!blocking || self.left_turn) { pull(&mut self.left, &self.left_slot, blocking); }
+            if pull_right && (!blocking || !self.left_turn) { pull(&mut self.right, &self.right_slot, blocking); }
+            self.left_turn = !self.left_turn;
+        } else if blocking {
+            self.subscription.wait();
+        }
+
+        let err = self.left_slot.borrow_mut().take_error().or_else(|| self.right_slot.borrow_mut().take_error());
+        if let Some(e) = err {
+            self.subscriber.as_mut().unwrap().on_error(e);
+            return self.release()
+        }
+
+        while self.subscription.demand() > 0 {
+            let l = self.left_slot.borrow_mut().pop();
+            let r = if l.is_none() { self.right_slot.borrow_mut().pop() } else { None };
+            match (l, r) {
+                (Some(l), _) => self.latest_left = Some(l),
+                (None, Some(r)) => self.latest_right = Some(r),
+                (None, None) => break
+            }
+            if !self.emit() {
+                return self.release()
+            }
+        }
+
+        let (ld, rd) = (self.left_slot.borrow().is_drained(), self.right_slot.borrow().is_drained());
+        if (ld && rd) || (ld && self.latest_left.is_none()) || (rd && self.latest_right.is_none()) {
+            self.subscriber.as_mut().unwrap().on_complete(false);
+            return self.release()
+        }
+        true
+    }
+}
+
+impl<'a, A, B, E> Publisher<'a> for CombineLatest<'a, A, B, E>
+where A : Publisher<'a, Error=E>, B : Publisher<'a, Error=E>,
+      <A as Publisher<'a>>::Output : Clone + 'a, <B as Publisher<'a>>::Output : Clone + 'a, E : Debug + 'a
+{
+    type Output = (<A as Publisher<'a>>::Output, <B as Publisher<'a>>::Output);
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=(<A as Publisher<'a>>::Output, <B as Publisher<'a>>::Output), Error=E> + 'a>) {
+        self.subscription = Subscription::new(0);
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
+    }
+
+    fn next(&mut self) -> bool {
+        self.step(true)
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.step(false)
+    }
+}
+
+//
+// Select
+//
+
+/// Select
+/// takes items from any number of publishers of the same type, the
+/// InputStrategy decides which input each item is taken from.
+/// Inputs are polled with try_next, so none of them can hold up the others.
+/// Completes once every input has completed. An error from an input is
+/// passed on straight away if fail_fast is set, otherwise the first error
+/// is held back until the remaining inputs have completed
+pub struct Select<'a, T, E, S> where S : InputStrategy {
+    inputs: Vec<Inlet<'a, T, E>>,
+    strategy: S,
+    stamp: u64,
+    fail_fast: bool,
+    error: Option<E>,
+    subscriber: Option<Box<Subscriber<Input=T, Error=E> + 'a>>,
+    subscription: Subscription
+}
+
+impl<'a, T, E, S> Select<'a, T, E, S> where T : 'a, E : Debug + 'a, S : InputStrategy {
+    pub fn new(strategy: S) -> Select<'a, T, E, S> {
+        Select {
+            inputs: Vec::new(),
+            strategy: strategy,
+            stamp: 0,
+            fail_fast: false,
+            error: None,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    /// A Select which gives up as soon as any of its inputs errors
+    pub fn fail_fast(strategy: S) -> Select<'a, T, E, S> {
+        let mut s = Select::new(strategy);
+        s.fail_fast = true;
+        s
+    }
+
+    /// Adds another input, ranked after those already added
+    pub fn add<P>(&mut self, p: P) where P : Publisher<'a, Output=T, Error=E> + 'a {
+        self.inputs.push(Inlet::new(p));
+    }
+
+    pub fn len(&self) -> usize {
+        self.inputs.len()
+    }
+
+    /// Drops the subscriber and tells every input to stop
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        for input in self.inputs.iter_mut() {
+            input.cancel();
+        }
+        self.inputs.clear();
+        false
+    }
+
+    /// Passes on at most one item, returns whether it did.
+    /// Without demand the inputs are still polled, though nothing is taken
+    /// from them, so that completion is passed on once they have all ended
+    fn step(&mut self) -> bool {
+        if self.subscription.is_cancelled() { return self.release() }
+        if self.subscriber.is_none() { error!("My subscriber went away"); return false }
+
+        self.stamp += 1;
+        let chosen = if self.subscription.demand() > 0 {
+            self.strategy.select(self.inputs.as_mut_slice(), self.stamp)
+        } else {
+            for input in self.inputs.iter_mut() {
+                input.poll(self.stamp);
+            }
+            None
+        };
+
+        for i in range(0, self.inputs.len()) {
+            if let Some(e) = self.inputs[i].take_error() {
+                if self.fail_fast {
+                    self.subscriber.as_mut().unwrap().on_error(e);
+                    return self.release()
+                }
+                if self.error.is_none() { self.error = Some(e); }
+            }
+        }
+
+        let mut delivered = false;
+        if let Some(t) = chosen.and_then(|i| self.inputs[i].pop()) {
+            self.subscription.take();
+            delivered = true;
+            if !self.subscriber.as_mut().unwrap().on_next(t) {
+                return self.release()
+            }
+        }
+
+        self.inputs.retain(|input| !input.is_drained());
+        if self.inputs.is_empty() {
+            match self.error.take() {
+                Some(e) => self.subscriber.as_mut().unwrap().on_error(e),
+                None => self.subscriber.as_mut().unwrap().on_complete(false)
+            }
+            return self.release()
+        }
+        delivered
+    }
+}
+
+impl<'a, T, E, S> Publisher<'a> for Select<'a, T, E, S> where T : 'a, E : Debug + 'a, S : InputStrategy {
+    type Output = T;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=T, Error=E> + 'a>) {
+        self.subscription = Subscription::new(0);
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
+    }
+
+    // Blocking on any one input would starve the others,
+    // so keep polling until something turns up
+    fn next(&mut self) -> bool {
+        loop {
+            let delivered = self.step();
+            if self.subscriber.is_none() { return false }
+            if delivered { return true }
+            if self.subscription.demand() == 0 {
+                self.subscription.wait();
+                return true
+            }
+            Thread::yield_now();
+        }
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.step();
+        self.subscriber.is_some()
+    }
+}
+
+//
+// Merge
+//
+
+/// Merge
+/// flattens any number of publishers of the same type into one stream.
+/// It is a Select whose inputs take turns, so a busy input
+/// cannot starve a quiet one
+pub struct Merge<'a, T, E> {
+    inner: Select<'a, T, E, RoundRobinInput>
+}
+
+impl<'a, T, E> Merge<'a, T, E> where T : 'a, E : Debug + 'a {
+    pub fn new() -> Merge<'a, T, E> {
+        Merge {
+            inner: Select::new(RoundRobinInput::new())
+        }
+    }
+
+    /// A Merge which gives up as soon as any of its inputs errors
+    pub fn fail_fast() -> Merge<'a, T, E> {
+        Merge {
+            inner: Select::fail_fast(RoundRobinInput::new())
+        }
+    }
+
+    /// Adds another input, it will be polled from the next turn on
+    pub fn add<P>(&mut self, p: P) where P : Publisher<'a, Output=T, Error=E> + 'a {
+        self.inner.add(p);
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+impl<'a, T, E> Publisher<'a> for Merge<'a, T, E> where T : 'a, E : Debug + 'a {
+    type Output = T;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=T, Error=E> + 'a>) {
+        self.inner.subscribe(s);
+    }
+
+    fn next(&mut self) -> bool {
+        self.inner.next()
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.inner.try_next()
+    }
+}
+
+//
+// Interval
+//
+
+/// Interval
+/// emits 0, 1, 2, ... one every period, the first a period after subscribing.
+/// next sleeps until the next tick is due, try_next only emits if it already is,
+/// so it can be driven by a scheduler loop or by NetEngine::drive.
+/// A tick which finds no demand waits for it, and ticks missed while
+/// waiting are skipped rather than sent in a burst
+pub struct Interval<'a, E, C = RealClock> where C : Clock {
+    period_ns: u64,
+    due_ns: u64,
+    count: u64,
+    clock: C,
+    subscriber: Option<Box<Subscriber<Input=u64, Error=E> + 'a>>,
+    subscription: Subscription
+}
+
+impl<'a, E> Interval<'a, E, RealClock> {
+    pub fn new(period: Duration) -> Interval<'a, E, RealClock> {
+        Interval::with_clock(period, RealClock)
+    }
+}
+
+impl<'a, E, C> Interval<'a, E, C> where C : Clock {
+    pub fn with_clock(period: Duration, clock: C) -> Interval<'a, E, C> {
+        Interval {
+            period_ns: clamped_ns(period),
+            due_ns: 0,
+            count: 0,
+            clock: clock,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    /// Drops the subscriber, and with it the rest of the chain
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        false
+    }
+
+    fn tick(&mut self, now: u64) -> bool {
+        if !self.subscription.take() { return true }
+        let more = match self.subscriber.as_mut() {
+            Some(s) => s.on_next(self.count),
             None => { error!("My subscriber went away"); false }
+        };
+        self.count += 1;
+        self.due_ns += self.period_ns;
+        if self.due_ns <= now {
+            self.due_ns = now + self.period_ns;
         }
+        if !more { self.release(); }
+        more
     }
 }
 
+impl<'a, E, C> Publisher<'a> for Interval<'a, E, C> where C : Clock {
+    type Output = u64;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=u64, Error=E> + 'a>) {
+        self.subscription = Subscription::new(0);
+        self.due_ns = self.clock.now_ns() + self.period_ns;
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
+    }
+
+    fn next(&mut self) -> bool {
+        if self.subscription.is_cancelled() { return self.release() }
+        let now = self.clock.now_ns();
+        if now < self.due_ns {
+            self.clock.sleep_ns(self.due_ns - now);
+        }
+        // once past due, there is nothing to wait for but demand
+        if self.subscription.demand() == 0 { Thread::yield_now(); return true }
+        let now = self.clock.now_ns();
+        self.tick(now)
+    }
 
+    // Does not block
+    fn try_next(&mut self) -> bool {
+        if self.subscription.is_cancelled() { return self.release() }
+        let now = self.clock.now_ns();
+        if now < self.due_ns { return true }
+        self.tick(now)
+    }
+}
+
+//
+// Timer
+//
+
+/// Timer
+/// emits a single 0 once delay has passed since subscribing, then completes.
+/// Like Interval, next sleeps until it is due and try_next does not
+pub struct Timer<'a, E, C = RealClock> where C : Clock {
+    delay_ns: u64,
+    due_ns: u64,
+    clock: C,
+    subscriber: Option<Box<Subscriber<Input=u64, Error=E> + 'a>>,
+    subscription: Subscription
+}
+
+impl<'a, E> Timer<'a, E, RealClock> {
+    pub fn new(delay: Duration) -> Timer<'a, E, RealClock> {
+        Timer::with_clock(delay, RealClock)
+    }
+}
+
+impl<'a, E, C> Timer<'a, E, C> where C : Clock {
+    pub fn with_clock(delay: Duration, clock: C) -> Timer<'a, E, C> {
+        Timer {
+            delay_ns: clamped_ns(delay),
+            due_ns: 0,
+            clock: clock,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    /// Drops the subscriber, and with it the rest of the chain
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        false
+    }
+
+    fn fire(&mut self) -> bool {
+        if !self.subscription.take() { return true }
+        if let Some(s) = self.subscriber.as_mut() {
+            if s.on_next(0) {
+                s.on_complete(false);
+            }
+        }
+        self.release()
+    }
+}
+
+impl<'a, E, C> Publisher<'a> for Timer<'a, E, C> where C : Clock {
+    type Output = u64;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=u64, Error=E> + 'a>) {
+        self.subscription = Subscription::new(0);
+        self.due_ns = self.clock.now_ns() + self.delay_ns;
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
+    }
+
+    fn next(&mut self) -> bool {
+        if self.subscription.is_cancelled() || self.subscriber.is_none() { return self.release() }
+        let now = self.clock.now_ns();
+        if now < self.due_ns {
+            self.clock.sleep_ns(self.due_ns - now);
+        }
+        if self.subscription.demand() == 0 { Thread::yield_now(); return true }
+        self.fire()
+    }
+
+    // Does not block
+    fn try_next(&mut self) -> bool {
+        if self.subscription.is_cancelled() || self.subscriber.is_none() { return self.release() }
+        if self.clock.now_ns() < self.due_ns { return true }
+        self.fire()
+    }
+}
+
+//
+// Ticked
+//
+
+// Hands what the upstream sends on to a stage which the Ticked also holds,
+// counting the items so the Ticked can tell whether anything arrived
+struct TickedStage<S> {
+    stage: Rc<RefCell<S>>,
+    arrived: Rc<Cell<u64>>
+}
+
+impl<S> Subscriber for TickedStage<S> where S : Subscriber {
+    type Input = <S as Subscriber>::Input;
+    type Error = <S as Subscriber>::Error;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        self.stage.borrow_mut().on_subscribe(sub);
+    }
+
+    fn on_next(&mut self, t: <S as Subscriber>::Input) -> bool {
+        self.arrived.set(self.arrived.get() + 1);
+        self.stage.borrow_mut().on_next(t)
+    }
+
+    fn on_error(&mut self, err: <S as Subscriber>::Error) {
+        self.stage.borrow_mut().on_error(err);
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        self.stage.borrow_mut().on_complete(force);
+    }
+}
+
+/// Ticked
+/// A publisher with a processor hung off of it, as with a Chain, for a
+/// processor with something to do other than as items arrive, such as a
+/// Buffer with a time limit, or an Unzip holding back an item it has no
+/// demand for. The upstream is polled with try_next, so a quiet or blocking
+/// upstream cannot hold it up, and the processor is ticked before and after
+/// every poll, so what it holds back goes out ahead of anything new. Once the
+/// upstream is done the processor is still ticked, until it has nothing more
+/// to do. next waits a while when nothing arrived, though not past the time
+/// the processor next has something due
+pub struct Ticked<P, S> {
+    upstream: P,
+    stage: Rc<RefCell<S>>,
+    arrived: Rc<Cell<u64>>,
+    subscribed: bool,
+    upstream_done: bool
+}
+
+impl<P, S> Ticked<P, S> {
+    pub fn new(upstream: P, stage: S) -> Ticked<P, S> {
+        Ticked {
+            upstream: upstream,
+            stage: Rc::new(RefCell::new(stage)),
+            arrived: Rc::new(Cell::new(0)),
+            subscribed: false,
+            upstream_done: false
+        }
+    }
+}
+
+impl<'a, P, S> Publisher<'a> for Ticked<P, S>
+where P : Publisher<'a>,
+      S : Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> +
+          Publisher<'a, Error=<P as Publisher<'a>>::Error> + Tick + 'a
+{
+    type Output = <S as Publisher<'a>>::Output;
+    type Error = <P as Publisher<'a>>::Error;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=<S as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>) {
+        if self.subscribed {
+            panic!("subscribe called twice on a ticked chain, its processor has already been handed upstream")
+        }
+        self.subscribed = true;
+        self.stage.borrow_mut().subscribe(s);
+        self.upstream.subscribe(Box::new(TickedStage { stage: self.stage.clone(), arrived: self.arrived.clone() }));
+    }
+
+    fn next(&mut self) -> bool {
+        let before = self.arrived.get();
+        let more = self.try_next();
+        if more && self.arrived.get() == before {
+            self.stage.borrow().idle();
+        }
+        more
+    }
+
+    fn try_next(&mut self) -> bool {
+        if !self.stage.borrow_mut().tick() {
+            return false
+        }
+        if !self.upstream_done && !self.upstream.try_next() {
+            self.upstream_done = true;
+        }
+        self.stage.borrow_mut().tick()
+    }
+}
+
+//
+// Debounce
+//
+
+/// Debounce
+/// passes an item on only once its upstream has been quiet for the given
+/// time since it arrived, an item followed more quickly by another is dropped.
+/// The upstream is polled with try_next. When it completes, whatever item
+/// is waiting is passed on straight away
+pub struct Debounce<'a, P, C = RealClock> where P : Publisher<'a>, C : Clock {
+    upstream: P,
+    slot: Rc<RefCell<Slot<<P as Publisher<'a>>::Output, <P as Publisher<'a>>::Error>>>,
+    quiet_ns: u64,
+    pending: Option<<P as Publisher<'a>>::Output>,
+    due_ns: u64,
+    clock: C,
+    subscriber: Option<Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>>,
+    subscription: Subscription
+}
+
+impl<'a, P> Debounce<'a, P, RealClock>
+where P : Publisher<'a>, <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    pub fn new(upstream: P, quiet: Duration) -> Debounce<'a, P, RealClock> {
+        Debounce::with_clock(upstream, quiet, RealClock)
+    }
+}
+
+impl<'a, P, C> Debounce<'a, P, C>
+where P : Publisher<'a>, C : Clock, <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    pub fn with_clock(mut upstream: P, quiet: Duration, clock: C) -> Debounce<'a, P, C> {
+        let slot = slot_for(&mut upstream);
+        Debounce {
+            upstream: upstream,
+            slot: slot,
+            quiet_ns: clamped_ns(quiet),
+            pending: None,
+            due_ns: 0,
+            clock: clock,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    /// Drops the subscriber and tells the upstream to stop
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        self.slot.borrow_mut().cancel();
+        false
+    }
+
+    /// Returns whether to carry on, and whether anything happened
+    fn step(&mut self) -> (bool, bool) {
+        if self.subscription.is_cancelled() { return (self.release(), false) }
+        if self.subscriber.is_none() { error!("My subscriber went away"); return (false, false) }
+
+        pull(&mut self.upstream, &self.slot, false);
+        let now = self.clock.now_ns();
+        let mut busy = false;
+        loop {
+            let t = self.slot.borrow_mut().pop();
+            match t {
+                Some(t) => { self.pending = Some(t); self.due_ns = now + self.quiet_ns; busy = true },
+                None => break
+            }
+        }
+
+        let err = self.slot.borrow_mut().take_error();
+        if let Some(e) = err {
+            self.subscriber.as_mut().unwrap().on_error(e);
+            return (self.release(), true)
+        }
+
+        let done = self.slot.borrow().is_drained();
+        if self.pending.is_some() && (done || now >= self.due_ns) && self.subscription.take() {
+            let t = self.pending.take().unwrap();
+            busy = true;
+            if !self.subscriber.as_mut().unwrap().on_next(t) {
+                return (self.release(), true)
+            }
+        }
+        if done && self.pending.is_none() {
+            self.subscriber.as_mut().unwrap().on_complete(false);
+            return (self.release(), true)
+        }
+        (true, busy)
+    }
+}
+
+impl<'a, P, C> Publisher<'a> for Debounce<'a, P, C>
+where P : Publisher<'a>, C : Clock, <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    type Output = <P as Publisher<'a>>::Output;
+    type Error = <P as Publisher<'a>>::Error;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>) {
+        self.subscription = Subscription::new(0);
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
+    }
+
+    fn next(&mut self) -> bool {
+        let (more, busy) = self.step();
+        if more && !busy {
+            let due = if self.pending.is_some() { Some(self.due_ns) } else { None };
+            idle(&self.clock, due);
+        }
+        more
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.step().0
+    }
+}
+
+//
+// Sample
+//
+
+/// Sample
+/// passes on the latest item from its upstream once every period,
+/// as long as a new one has arrived since the last time.
+/// The upstream is polled with try_next. An item still waiting for
+/// its turn when the upstream completes is not passed on
+pub struct Sample<'a, P, C = RealClock> where P : Publisher<'a>, C : Clock {
+    upstream: P,
+    slot: Rc<RefCell<Slot<<P as Publisher<'a>>::Output, <P as Publisher<'a>>::Error>>>,
+    period_ns: u64,
+    latest: Option<<P as Publisher<'a>>::Output>,
+    due_ns: u64,
+    clock: C,
+    subscriber: Option<Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>>,
+    subscription: Subscription
+}
+
+impl<'a, P> Sample<'a, P, RealClock>
+where P : Publisher<'a>, <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    pub fn new(upstream: P, period: Duration) -> Sample<'a, P, RealClock> {
+        Sample::with_clock(upstream, period, RealClock)
+    }
+}
+
+impl<'a, P, C> Sample<'a, P, C>
+where P : Publisher<'a>, C : Clock, <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    pub fn with_clock(mut upstream: P, period: Duration, clock: C) -> Sample<'a, P, C> {
+        let slot = slot_for(&mut upstream);
+        Sample {
+            upstream: upstream,
+            slot: slot,
+            period_ns: clamped_ns(period),
+            latest: None,
+            due_ns: 0,
+            clock: clock,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    /// Drops the subscriber and tells the upstream to stop
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        self.slot.borrow_mut().cancel();
+        false
+    }
+
+    /// Returns whether to carry on, and whether anything happened
+    fn step(&mut self) -> (bool, bool) {
+        if self.subscription.is_cancelled() { return (self.release(), false) }
+        if self.subscriber.is_none() { error!("My subscriber went away"); return (false, false) }
+
+        pull(&mut self.upstream, &self.slot, false);
+        let mut busy = false;
+        loop {
+            let t = self.slot.borrow_mut().pop();
+            match t {
+                Some(t) => { self.latest = Some(t); busy = true },
+                None => break
+            }
+        }
+
+        let err = self.slot.borrow_mut().take_error();
+        if let Some(e) = err {
+            self.subscriber.as_mut().unwrap().on_error(e);
+            return (self.release(), true)
+        }
+        if self.slot.borrow().is_drained() {
+            self.subscriber.as_mut().unwrap().on_complete(false);
+            return (self.release(), true)
+        }
+
+        let now = self.clock.now_ns();
+        if now >= self.due_ns {
+            // ticks missed while nobody was polling are skipped
+            self.due_ns += self.period_ns;
+            if self.due_ns <= now {
+                self.due_ns = now + self.period_ns;
+            }
+            if self.latest.is_some() && self.subscription.take() {
+                let t = self.latest.take().unwrap();
+                busy = true;
+                if !self.subscriber.as_mut().unwrap().on_next(t) {
+                    return (self.release(), true)
+                }
+            }
+        }
+        (true, busy)
+    }
+}
+
+impl<'a, P, C> Publisher<'a> for Sample<'a, P, C>
+where P : Publisher<'a>, C : Clock, <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    type Output = <P as Publisher<'a>>::Output;
+    type Error = <P as Publisher<'a>>::Error;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>) {
+        self.subscription = Subscription::new(0);
+        self.due_ns = self.clock.now_ns() + self.period_ns;
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
+    }
+
+    fn next(&mut self) -> bool {
+        let (more, busy) = self.step();
+        if more && !busy {
+            idle(&self.clock, Some(self.due_ns));
+        }
+        more
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.step().0
+    }
+}
+
+//
+// ThrottleLast
+//
+
+/// ThrottleLast
+/// passes on at most one item per window, the last to arrive in it.
+/// A window opens with the first item to arrive while none is open,
+/// unlike Sample whose ticks carry on regardless of what arrives.
+/// The upstream is polled with try_next. When it completes, the last
+/// item of the window still open is passed on straight away
+pub struct ThrottleLast<'a, P, C = RealClock> where P : Publisher<'a>, C : Clock {
+    upstream: P,
+    slot: Rc<RefCell<Slot<<P as Publisher<'a>>::Output, <P as Publisher<'a>>::Error>>>,
+    window_ns: u64,
+    latest: Option<<P as Publisher<'a>>::Output>,
+    closes_ns: Option<u64>,
+    clock: C,
+    subscriber: Option<Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>>,
+    subscription: Subscription
+}
+
+impl<'a, P> ThrottleLast<'a, P, RealClock>
+where P : Publisher<'a>, <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    pub fn new(upstream: P, window: Duration) -> ThrottleLast<'a, P, RealClock> {
+        ThrottleLast::with_clock(upstream, window, RealClock)
+    }
+}
+
+impl<'a, P, C> ThrottleLast<'a, P, C>
+where P : Publisher<'a>, C : Clock, <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    pub fn with_clock(mut upstream: P, window: Duration, clock: C) -> ThrottleLast<'a, P, C> {
+        let slot = slot_for(&mut upstream);
+        ThrottleLast {
+            upstream: upstream,
+            slot: slot,
+            window_ns: clamped_ns(window),
+            latest: None,
+            closes_ns: None,
+            clock: clock,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    /// Drops the subscriber and tells the upstream to stop
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        self.slot.borrow_mut().cancel();
+        false
+    }
+
+    /// Returns whether to carry on, and whether anything happened
+    fn step(&mut self) -> (bool, bool) {
+        if self.subscription.is_cancelled() { return (self.release(), false) }
+        if self.subscriber.is_none() { error!("My subscriber went away"); return (false, false) }
+
+        pull(&mut self.upstream, &self.slot, false);
+        let now = self.clock.now_ns();
+        let mut busy = false;
+        loop {
+            let t = self.slot.borrow_mut().pop();
+            match t {
+                Some(t) => {
+                    if self.closes_ns.is_none() { self.closes_ns = Some(now + self.window_ns); }
+                    self.latest = Some(t);
+                    busy = true
+                },
+                None => break
+            }
+        }
+
+        let err = self.slot.borrow_mut().take_error();
+        if let Some(e) = err {
+            self.subscriber.as_mut().unwrap().on_error(e);
+            return (self.release(), true)
+        }
+
+        let done = self.slot.borrow().is_drained();
+        let closed = self.closes_ns.map_or(false, |c| now >= c);
+        if self.latest.is_some() && (done || closed) && self.subscription.take() {
+            let t = self.latest.take().unwrap();
+            self.closes_ns = None;
+            busy = true;
+            if !self.subscriber.as_mut().unwrap().on_next(t) {
+                return (self.release(), true)
+            }
+        }
+        if done && self.latest.is_none() {
+            self.subscriber.as_mut().unwrap().on_complete(false);
+            return (self.release(), true)
+        }
+        (true, busy)
+    }
+}
+
+impl<'a, P, C> Publisher<'a> for ThrottleLast<'a, P, C>
+where P : Publisher<'a>, C : Clock, <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    type Output = <P as Publisher<'a>>::Output;
+    type Error = <P as Publisher<'a>>::Error;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>) {
+        self.subscription = Subscription::new(0);
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
+    }
+
+    fn next(&mut self) -> bool {
+        let (more, busy) = self.step();
+        if more && !busy {
+            idle(&self.clock, self.closes_ns);
+        }
+        more
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.step().0
+    }
+}
+
+//
+// Retry
+//
+
+/// Retry
+/// resubscribes to a fresh upstream when the current one errors.
+/// Publishers cannot be subscribed to twice, so it takes a factory which
+/// makes a new upstream for each attempt. Retry::new gives up after a number
+/// of retries, Retry::when asks a backoff function, given the number of the
+/// attempt which failed and its error, how long to wait before the next,
+/// or None to give up. Giving up passes the error on. An upstream
+/// given to starting_with is used for the first attempt instead
+pub struct Retry<'a, P, F, C = RealClock>
+where P : Publisher<'a>, F : Fn() -> P, C : Clock
+{
+    factory: F,
+    first: Option<P>,
+    backoff: Box<FnMut(usize, &<P as Publisher<'a>>::Error) -> Option<Duration> + 'a>,
+    upstream: Option<P>,
+    state: Rc<RefCell<RelayState<<P as Publisher<'a>>::Error>>>,
+    attempt: usize,
+    resume_ns: u64,
+    clock: C,
+    subscriber: Option<Rc<RefCell<Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>>>>,
+    subscription: Subscription
+}
+
+impl<'a, P, F> Retry<'a, P, F, RealClock>
+where P : Publisher<'a>, F : Fn() -> P,
+      <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    /// Retries straight away, up to times times
+    pub fn new(factory: F, times: usize) -> Retry<'a, P, F, RealClock> {
+        Retry::when(factory, move |&mut: attempt: usize, _: &<P as Publisher<'a>>::Error| {
+            if attempt <= times { Some(Duration::zero()) } else { None }
+        })
+    }
+
+    /// Retries after however long backoff says, for as long as it says
+    pub fn when<B>(factory: F, backoff: B) -> Retry<'a, P, F, RealClock>
+    where B : FnMut(usize, &<P as Publisher<'a>>::Error) -> Option<Duration> + 'a
+    {
+        Retry {
+            factory: factory,
+            first: None,
+            backoff: Box::new(backoff),
+            upstream: None,
+            state: Rc::new(RefCell::new(RelayState::new())),
+            attempt: 0,
+            resume_ns: 0,
+            clock: RealClock,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+}
+
+impl<'a, P, F, C> Retry<'a, P, F, C>
+where P : Publisher<'a>, F : Fn() -> P, C : Clock,
+      <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    /// The same Retry, timing its backoff with another clock
+    pub fn with_clock<C2>(self, clock: C2) -> Retry<'a, P, F, C2> where C2 : Clock {
+        Retry {
+            factory: self.factory,
+            first: self.first,
+            backoff: self.backoff,
+            upstream: self.upstream,
+            state: self.state,
+            attempt: self.attempt,
+            resume_ns: self.resume_ns,
+            clock: clock,
+            subscriber: self.subscriber,
+            subscription: self.subscription
+        }
+    }
+
+    /// The same Retry, making its first attempt on an upstream
+    /// which already exists, and only calling the factory after that
+    pub fn starting_with(mut self, first: P) -> Retry<'a, P, F, C> {
+        self.first = Some(first);
+        self
+    }
+
+    /// Makes a new upstream and relays it to our subscriber
+    fn connect(&mut self) {
+        let shared = match self.subscriber.as_ref() {
+            Some(s) => s.clone(),
+            None => return
+        };
+        self.state = Rc::new(RefCell::new(RelayState::new()));
+        let mut p = match self.first.take() {
+            Some(p) => p,
+            None => (self.factory)()
+        };
+        p.subscribe(Box::new(Relay::counted(shared, self.state.clone(), self.subscription.clone())));
+        self.upstream = Some(p);
+    }
+
+    /// Drops the subscriber and the upstream
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        self.upstream = None;
+        false
+    }
+
+    fn step(&mut self, blocking: bool) -> bool {
+        if self.subscription.is_cancelled() { return self.release() }
+        if self.subscriber.is_none() { error!("My subscriber went away"); return false }
+
+        if self.upstream.is_none() {
+            let now = self.clock.now_ns();
+            if now < self.resume_ns {
+                if !blocking { return true }
+                self.clock.sleep_ns(self.resume_ns - now);
+            }
+            self.connect();
+        }
+        if self.subscription.demand() == 0 {
+            if blocking { Thread::yield_now(); }
+            return true
+        }
+
+        let more = match self.upstream.as_mut() {
+            Some(p) => if blocking { p.next() } else { p.try_next() },
+            None => false
+        };
+
+        let err = self.state.borrow_mut().take_error();
+        if let Some(e) = err {
+            self.upstream = None;
+            self.attempt += 1;
+            return match (*self.backoff).call_mut((self.attempt, &e)) {
+                Some(wait) => {
+                    info!("Upstream failed, retrying");
+                    self.resume_ns = self.clock.now_ns() + clamped_ns(wait);
+                    true
+                },
+                None => {
+                    self.subscriber.as_ref().unwrap().borrow_mut().on_error(e);
+                    self.release()
+                }
+            }
+        }
+        if self.state.borrow().is_stopped() {
+            return self.release()
+        }
+        if !more || self.state.borrow().is_completed() {
+            self.subscriber.as_ref().unwrap().borrow_mut().on_complete(false);
+            return self.release()
+        }
+        true
+    }
+}
+
+impl<'a, P, F, C> Publisher<'a> for Retry<'a, P, F, C>
+where P : Publisher<'a>, F : Fn() -> P, C : Clock,
+      <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    type Output = <P as Publisher<'a>>::Output;
+    type Error = <P as Publisher<'a>>::Error;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>) {
+        let mut s: Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error>+'a> = s;
+        self.subscription = Subscription::new(0);
+        s.on_subscribe(self.subscription.clone());
+        self.subscriber = Some(Rc::new(RefCell::new(s)));
+        self.attempt = 0;
+        self.connect();
+    }
+
+    fn next(&mut self) -> bool {
+        self.step(true)
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.step(false)
+    }
+}
+
+//
+// OnErrorResume
+//
+
+/// OnErrorResume
+/// passes on everything from its upstream until that errors, then
+/// makes a fallback publisher from the error and carries on with that.
+/// Only one switch is made, an error from the fallback is passed on
+pub struct OnErrorResume<'a, P, Q, F>
+where P : Publisher<'a>,
+      Q : Publisher<'a, Output=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error>,
+      F : FnOnce(<P as Publisher<'a>>::Error) -> Q
+{
+    primary: Option<P>,
+    fallback: Option<Q>,
+    make_fallback: Option<F>,
+    state: Rc<RefCell<RelayState<<P as Publisher<'a>>::Error>>>,
+    subscriber: Option<Rc<RefCell<Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>>>>,
+    subscription: Subscription
+}
+
+impl<'a, P, Q, F> OnErrorResume<'a, P, Q, F>
+where P : Publisher<'a>,
+      Q : Publisher<'a, Output=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error>,
+      F : FnOnce(<P as Publisher<'a>>::Error) -> Q,
+      <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    pub fn new(upstream: P, fallback: F) -> OnErrorResume<'a, P, Q, F> {
+        OnErrorResume {
+            primary: Some(upstream),
+            fallback: None,
+            make_fallback: Some(fallback),
+            state: Rc::new(RefCell::new(RelayState::new())),
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    fn relay(&mut self) -> Option<Relay<'a, <P as Publisher<'a>>::Output, <P as Publisher<'a>>::Error>> {
+        self.state = Rc::new(RefCell::new(RelayState::new()));
+        self.subscriber.as_ref().map(|s| Relay::counted(s.clone(), self.state.clone(), self.subscription.clone()))
+    }
+
+    /// Drops the subscriber and both upstreams
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        self.primary = None;
+        self.fallback = None;
+        false
+    }
+
+    fn step(&mut self, blocking: bool) -> bool {
+        if self.subscription.is_cancelled() { return self.release() }
+        if self.subscriber.is_none() { error!("My subscriber went away"); return false }
+        if self.subscription.demand() == 0 {
+            if blocking { Thread::yield_now(); }
+            return true
+        }
+
+        let more = match (self.primary.as_mut(), self.fallback.as_mut()) {
+            (Some(p), _) => if blocking { p.next() } else { p.try_next() },
+            (None, Some(q)) => if blocking { q.next() } else { q.try_next() },
+            (None, None) => false
+        };
+
+        let err = self.state.borrow_mut().take_error();
+        if let Some(e) = err {
+            if self.primary.is_none() || self.make_fallback.is_none() {
+                self.subscriber.as_ref().unwrap().borrow_mut().on_error(e);
+                return self.release()
+            }
+            info!("Upstream failed, switching to the fallback");
+            self.primary = None;
+            let f = self.make_fallback.take().unwrap();
+            let mut q = f(e);
+            if let Some(relay) = self.relay() {
+                q.subscribe(Box::new(relay));
+            }
+            self.fallback = Some(q);
+            return true
+        }
+        if self.state.borrow().is_stopped() {
+            return self.release()
+        }
+        if !more || self.state.borrow().is_completed() {
+            self.subscriber.as_ref().unwrap().borrow_mut().on_complete(false);
+            return self.release()
+        }
+        true
+    }
+}
+
+impl<'a, P, Q, F> Publisher<'a> for OnErrorResume<'a, P, Q, F>
+where P : Publisher<'a>,
+      Q : Publisher<'a, Output=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error>,
+      F : FnOnce(<P as Publisher<'a>>::Error) -> Q,
+      <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    type Output = <P as Publisher<'a>>::Output;
+    type Error = <P as Publisher<'a>>::Error;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>) {
+        let mut s: Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error>+'a> = s;
+        self.subscription = Subscription::new(0);
+        s.on_subscribe(self.subscription.clone());
+        self.subscriber = Some(Rc::new(RefCell::new(s)));
+        if let Some(relay) = self.relay() {
+            if let Some(p) = self.primary.as_mut() {
+                p.subscribe(Box::new(relay));
+            }
+        }
+    }
+
+    fn next(&mut self) -> bool {
+        self.step(true)
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.step(false)
+    }
+}
+
+//
+// Timeout
+//
+
+/// Timeout
+/// passes on everything from its upstream, as long as no more than the
+/// given time goes by without an item while the subscriber is waiting for one.
+/// When it does, either the stream ends with a TimedOut error, or the upstream
+/// is dropped in favour of a fallback publisher, which is not timed.
+/// The upstream is polled with try_next, so an upstream which would block,
+/// such as a Coupler or NetStreamer, cannot hold it up. To time out while
+/// running inside the reactor, drive it with NetEngine::drive, or else run it
+/// from a scheduler, as it sets no timer of its own. A negative timeout is
+/// taken as 0
+pub struct Timeout<'a, P, Q, C = RealClock>
+where P : Publisher<'a>,
+      Q : Publisher<'a, Output=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error>,
+      C : Clock
+{
+    upstream: Option<P>,
+    fallback: Option<Q>,
+    switched: bool,
+    timed_out: Option<fn(TimedOut) -> <P as Publisher<'a>>::Error>,
+    slot: Rc<RefCell<Slot<<P as Publisher<'a>>::Output, <P as Publisher<'a>>::Error>>>,
+    timeout_ns: u64,
+    due_ns: u64,
+    clock: C,
+    subscriber: Option<Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>>,
+    subscription: Subscription
+}
+
+// With no fallback there is nothing to name as its type, so the
+// upstream's own type stands in for it, it is never constructed
+impl<'a, P> Timeout<'a, P, P, RealClock>
+where P : Publisher<'a>,
+      <P as Publisher<'a>>::Output : 'a,
+      <P as Publisher<'a>>::Error : FromError<TimedOut> + Debug + 'a
+{
+    /// Ends the stream with a TimedOut error
+    pub fn new(upstream: P, timeout: Duration) -> Timeout<'a, P, P, RealClock> {
+        Timeout::configured(upstream, timeout, None, Some(FromError::from_error))
+    }
+}
+
+impl<'a, P, Q> Timeout<'a, P, Q, RealClock>
+where P : Publisher<'a>,
+      Q : Publisher<'a, Output=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error>,
+      <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    /// Carries on with the fallback
+    pub fn or_else(upstream: P, timeout: Duration, fallback: Q) -> Timeout<'a, P, Q, RealClock> {
+        Timeout::configured(upstream, timeout, Some(fallback), None)
+    }
+
+    fn configured(mut upstream: P, timeout: Duration, fallback: Option<Q>,
+                  timed_out: Option<fn(TimedOut) -> <P as Publisher<'a>>::Error>) -> Timeout<'a, P, Q, RealClock> {
+        let slot = slot_for(&mut upstream);
+        Timeout {
+            upstream: Some(upstream),
+            fallback: fallback,
+            switched: false,
+            timed_out: timed_out,
+            slot: slot,
+            timeout_ns: clamped_ns(timeout),
+            due_ns: 0,
+            clock: RealClock,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+}
+
+impl<'a, P, Q, C> Timeout<'a, P, Q, C>
+where P : Publisher<'a>,
+      Q : Publisher<'a, Output=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error>,
+      C : Clock,
+      <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    /// The same Timeout, reading the time from another clock
+    pub fn with_clock<C2>(self, clock: C2) -> Timeout<'a, P, Q, C2> where C2 : Clock {
+        Timeout {
+            upstream: self.upstream,
+            fallback: self.fallback,
+            switched: self.switched,
+            timed_out: self.timed_out,
+            slot: self.slot,
+            timeout_ns: self.timeout_ns,
+            due_ns: self.due_ns,
+            clock: clock,
+            subscriber: self.subscriber,
+            subscription: self.subscription
+        }
+    }
+
+    /// Drops the subscriber and tells the upstream to stop
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.subscriber = None;
+        self.slot.borrow_mut().cancel();
+        self.upstream = None;
+        self.fallback = None;
+        false
+    }
+
+    /// Returns whether to carry on, and whether anything happened
+    fn step(&mut self) -> (bool, bool) {
+        if self.subscription.is_cancelled() { return (self.release(), false) }
+        if self.subscriber.is_none() { error!("My subscriber went away"); return (false, false) }
+
+        let now = self.clock.now_ns();
+        if self.subscription.demand() == 0 {
+            // nobody is waiting, so the upstream cannot be late
+            self.due_ns = now + self.timeout_ns;
+            return (true, false)
+        }
+
+        if self.switched {
+            if let Some(q) = self.fallback.as_mut() { pull(q, &self.slot, false); }
+        } else {
+            if let Some(p) = self.upstream.as_mut() { pull(p, &self.slot, false); }
+        }
+
+        let mut busy = false;
+        while self.subscription.demand() > 0 {
+            let t = self.slot.borrow_mut().pop();
+            match t {
+                Some(t) => {
+                    self.subscription.take();
+                    self.due_ns = now + self.timeout_ns;
+                    busy = true;
+                    if !self.subscriber.as_mut().unwrap().on_next(t) {
+                        return (self.release(), true)
+                    }
+                },
+                None => break
+            }
+        }
+
+        let err = self.slot.borrow_mut().take_error();
+        if let Some(e) = err {
+            self.subscriber.as_mut().unwrap().on_error(e);
+            return (self.release(), true)
+        }
+        if self.slot.borrow().is_drained() {
+            self.subscriber.as_mut().unwrap().on_complete(false);
+            return (self.release(), true)
+        }
+
+        if !self.switched && !busy && now >= self.due_ns {
+            if self.fallback.is_some() {
+                info!("Upstream timed out, switching to the fallback");
+                self.slot.borrow_mut().cancel();
+                self.upstream = None;
+                self.switched = true;
+                self.slot = slot_for(self.fallback.as_mut().unwrap());
+                return (true, true)
+            }
+            if let Some(timed_out) = self.timed_out {
+                self.subscriber.as_mut().unwrap().on_error(timed_out(TimedOut));
+            }
+            return (self.release(), true)
+        }
+        (true, busy)
+    }
+}
+
+impl<'a, P, Q, C> Publisher<'a> for Timeout<'a, P, Q, C>
+where P : Publisher<'a>,
+      Q : Publisher<'a, Output=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error>,
+      C : Clock,
+      <P as Publisher<'a>>::Output : 'a, <P as Publisher<'a>>::Error : Debug + 'a
+{
+    type Output = <P as Publisher<'a>>::Output;
+    type Error = <P as Publisher<'a>>::Error;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=<P as Publisher<'a>>::Output, Error=<P as Publisher<'a>>::Error> + 'a>) {
+        self.subscription = Subscription::new(0);
+        self.due_ns = self.clock.now_ns() + self.timeout_ns;
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
+    }
+
+    fn next(&mut self) -> bool {
+        let (more, busy) = self.step();
+        if more && !busy {
+            let due = if self.switched { None } else { Some(self.due_ns) };
+            idle(&self.clock, due);
+        }
+        more
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.step().0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use publisher::{IterPublisher, Coupler, Merge, Interval, Timer, Debounce, Sample, ThrottleLast, Retry, Timeout, PublisherExt};
+    use subscriber::Collect;
+    use reactive::{Publisher, Subscriber, Subscription, Notification};
+    use scheduler::VirtualScheduler;
+    use error::TimedOut;
+    use std::time::Duration;
+    use std::sync::mpsc::channel;
+    use std::cell::Cell;
+
+    // Asks for n items up front, and notes when it is told the stream is over
+    struct Exactly<'a> {
+        n: usize,
+        v: &'a mut Box<Vec<u32>>,
+        completed: &'a Cell<bool>
+    }
+
+    impl<'a> Subscriber for Exactly<'a> {
+        type Input = u32;
+        type Error = ();
+
+        fn on_subscribe(&mut self, sub: Subscription) {
+            sub.request(self.n);
+        }
+
+        fn on_next(&mut self, t: u32) -> bool {
+            self.v.push(t);
+            true
+        }
+
+        fn on_complete(&mut self, _: bool) {
+            self.completed.set(true);
+        }
+    }
+
+    #[test]
+    fn iter_completes_when_exactly_drained() {
+        let completed = Cell::new(false);
+        let mut v = Box::new(Vec::new());
+        {
+            let mut p = IterPublisher::new(range(0u32, 3));
+            p.subscribe(Box::new(Exactly { n: 3, v: &mut v, completed: &completed }));
+            p.run();
+        }
+        assert_eq!(*v, vec![0, 1, 2]);
+        assert!(completed.get());
+    }
+
+    #[test]
+    fn coupler_completes_when_exactly_drained() {
+        let (tx, rx) = channel();
+        let completed = Cell::new(false);
+        let mut v = Box::new(Vec::new());
+        {
+            tx.send(0u32).unwrap();
+            tx.send(1).unwrap();
+            drop(tx);
+            let mut p = Coupler::new(rx);
+            p.subscribe(Box::new(Exactly { n: 2, v: &mut v, completed: &completed }));
+            p.run();
+        }
+        assert_eq!(*v, vec![0, 1]);
+        assert!(completed.get());
+    }
+
+    #[test]
+    fn coupler_holds_back_what_is_not_asked_for() {
+        let (tx, rx) = channel();
+        let completed = Cell::new(false);
+        let mut v = Box::new(Vec::new());
+        {
+            tx.send(0u32).unwrap();
+            tx.send(1).unwrap();
+            drop(tx);
+            let mut p = Coupler::new(rx);
+            p.subscribe(Box::new(Exactly { n: 1, v: &mut v, completed: &completed }));
+            for _ in range(0, 5) { assert!(p.next()); }
+        }
+        // the end of the queue is behind an item nobody asked for
+        assert_eq!(*v, vec![0]);
+        assert!(!completed.get());
+    }
+
+    #[test]
+    fn merge_completes_when_exactly_drained() {
+        let completed = Cell::new(false);
+        let mut v = Box::new(Vec::new());
+        {
+            let mut m = Merge::new();
+            m.add(IterPublisher::new(range(0u32, 2)));
+            m.add(IterPublisher::new(range(10u32, 12)));
+            m.subscribe(Box::new(Exactly { n: 4, v: &mut v, completed: &completed }));
+            m.run();
+        }
+        assert_eq!(*v, vec![0, 10, 1, 11]);
+        assert!(completed.get());
+    }
+
+    #[test]
+    fn interval_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut iv = Interval::<(), _>::with_clock(Duration::seconds(1), sched.clock());
+            iv.subscribe(Box::new(Collect::new(&mut v)));
+            iv.try_next();
+            clock.advance(Duration::seconds(1));
+            iv.try_next();
+            iv.try_next();
+            clock.advance(Duration::milliseconds(1500));
+            iv.try_next();
+            // next waits by sleeping, which moves virtual time on
+            iv.next();
+            assert_eq!(sched.now_ns(), 3_000_000_000);
+        }
+        assert_eq!(*v, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn interval_past_due_waits_for_demand() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut iv = Interval::<(), _>::with_clock(Duration::seconds(1), sched.clock());
+            iv.subscribe(Box::new(Collect::with_demand(&mut v, 1)));
+            assert!(iv.next());
+            clock.advance(Duration::seconds(5));
+            assert!(iv.next());
+            assert!(iv.next());
+            assert_eq!(sched.now_ns(), 6_000_000_000);
+        }
+        assert_eq!(*v, vec![0]);
+    }
+
+    #[test]
+    fn debounce_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut db = Debounce::with_clock(Coupler::<u32, ()>::new(rx), Duration::milliseconds(100), sched.clock());
+            db.subscribe(Box::new(Collect::new(&mut v)));
+            tx.send(1).unwrap();
+            db.try_next();
+            clock.advance(Duration::milliseconds(50));
+            tx.send(2).unwrap();
+            db.try_next();
+            clock.advance(Duration::milliseconds(99));
+            db.try_next();
+            clock.advance(Duration::milliseconds(1));
+            db.try_next();
+            tx.send(3).unwrap();
+            db.try_next();
+            drop(tx);
+            while db.try_next() {}
+        }
+        assert_eq!(*v, vec![2, 3]);
+    }
+
+    #[test]
+    fn sample_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut s = Sample::with_clock(Coupler::<u32, ()>::new(rx), Duration::seconds(1), sched.clock());
+            s.subscribe(Box::new(Collect::new(&mut v)));
+            tx.send(1).unwrap();
+            s.try_next();
+            clock.advance(Duration::seconds(1));
+            s.try_next();
+            tx.send(2).unwrap();
+            s.try_next();
+            tx.send(3).unwrap();
+            s.try_next();
+            clock.advance(Duration::seconds(1));
+            s.try_next();
+            // nothing new arrived, so this tick passes nothing on
+            clock.advance(Duration::seconds(1));
+            s.try_next();
+            tx.send(4).unwrap();
+            s.try_next();
+            drop(tx);
+            while s.try_next() {}
+        }
+        // the 4 was still waiting for its tick at completion, so it is lost
+        assert_eq!(*v, vec![1, 3]);
+    }
+
+    #[test]
+    fn throttle_last_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = ThrottleLast::with_clock(Coupler::<u32, ()>::new(rx), Duration::seconds(1), sched.clock());
+            t.subscribe(Box::new(Collect::new(&mut v)));
+            tx.send(1).unwrap();
+            t.try_next();
+            clock.advance(Duration::milliseconds(500));
+            tx.send(2).unwrap();
+            t.try_next();
+            clock.advance(Duration::milliseconds(500));
+            t.try_next();
+            // nothing is open now, so the window opens with this one
+            clock.advance(Duration::seconds(5));
+            tx.send(3).unwrap();
+            t.try_next();
+            clock.advance(Duration::milliseconds(999));
+            t.try_next();
+            tx.send(4).unwrap();
+            t.try_next();
+            drop(tx);
+            while t.try_next() {}
+        }
+        // the last of the open window is passed on at completion
+        assert_eq!(*v, vec![2, 4]);
+    }
+
+    #[test]
+    fn throttle_last_with_a_negative_window() {
+        let sched = VirtualScheduler::new();
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = ThrottleLast::with_clock(Coupler::<u32, ()>::new(rx), Duration::seconds(-1), sched.clock());
+            t.subscribe(Box::new(Collect::new(&mut v)));
+            tx.send(1).unwrap();
+            t.try_next();
+            tx.send(2).unwrap();
+            t.try_next();
+            drop(tx);
+            while t.try_next() {}
+        }
+        assert_eq!(*v, vec![1, 2]);
+    }
+
+    #[test]
+    fn retry_backs_off_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let attempts = Cell::new(0u32);
+        let mut v = Box::new(Vec::new());
+        {
+            let make = || {
+                attempts.set(attempts.get() + 1);
+                let n = attempts.get();
+                let mut signals = vec![Notification::Next(n)];
+                if n < 3 { signals.push(Notification::Error(())); }
+                IterPublisher::new(signals.into_iter()).dematerialize()
+            };
+            // a second after the first failure, two after the second
+            let mut chain = Retry::when(make, |attempt: usize, _: &()| Some(Duration::seconds(attempt as i64)))
+                .with_clock(sched.clock())
+                .materialize();
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![Notification::Next(1), Notification::Next(2), Notification::Next(3), Notification::Complete]);
+        assert_eq!(sched.now_ns(), 3_000_000_000);
+    }
+
+    #[test]
+    fn retry_takes_a_negative_backoff_as_none() {
+        let sched = VirtualScheduler::new();
+        let mut v = Box::new(Vec::new());
+        {
+            let make = || IterPublisher::new(vec![Notification::Next(1u32), Notification::Error(())].into_iter()).dematerialize();
+            let mut chain = Retry::when(make, |attempt: usize, _: &()| if attempt < 2 { Some(Duration::seconds(-5)) } else { None })
+                .with_clock(sched.clock())
+                .materialize();
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![Notification::Next(1), Notification::Next(1), Notification::Error(()), Notification::Complete]);
+        assert_eq!(sched.now_ns(), 0);
+    }
+
+    #[test]
+    fn times_out_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = Timeout::new(Coupler::<u32, TimedOut>::new(rx), Duration::seconds(1))
+                .with_clock(sched.clock())
+                .materialize();
+            t.subscribe(Box::new(Collect::new(&mut v)));
+            tx.send(1).unwrap();
+            t.try_next();
+            clock.advance(Duration::milliseconds(999));
+            assert!(t.try_next());
+            clock.advance(Duration::milliseconds(1));
+            assert!(!t.try_next());
+        }
+        assert_eq!(*v, vec![Notification::Next(1), Notification::Error(TimedOut), Notification::Complete]);
+    }
+
+    #[test]
+    fn times_out_onto_the_fallback() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = Timeout::or_else(Coupler::<u32, ()>::new(rx), Duration::seconds(1), IterPublisher::new(range(10u32, 12)))
+                .with_clock(sched.clock());
+            t.subscribe(Box::new(Collect::new(&mut v)));
+            tx.send(1).unwrap();
+            t.try_next();
+            clock.advance(Duration::seconds(1));
+            t.try_next();
+            // the upstream has been dropped, so this never arrives
+            assert!(tx.send(2).is_err());
+            while t.try_next() {}
+        }
+        assert_eq!(*v, vec![1, 10, 11]);
+    }
+
+    #[test]
+    fn no_timeout_without_demand() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = Timeout::new(Coupler::<u32, TimedOut>::new(rx), Duration::seconds(1))
+                .with_clock(sched.clock())
+                .materialize();
+            t.subscribe(Box::new(Collect::with_demand(&mut v, 1)));
+            tx.send(1).unwrap();
+            t.try_next();
+            clock.advance(Duration::seconds(10));
+            assert!(t.try_next());
+        }
+        assert_eq!(*v, vec![Notification::Next(1)]);
+    }
+
+    #[test]
+    fn negative_timeout_is_taken_as_zero() {
+        let sched = VirtualScheduler::new();
+        let (_tx, rx) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = Timeout::new(Coupler::<u32, TimedOut>::new(rx), Duration::seconds(-1))
+                .with_clock(sched.clock())
+                .materialize();
+            t.subscribe(Box::new(Collect::new(&mut v)));
+            t.try_next();
+        }
+        assert_eq!(*v, vec![Notification::Error(TimedOut), Notification::Complete]);
+    }
+
+    #[test]
+    fn timer_fires_once_in_virtual_time() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = Timer::<(), _>::with_clock(Duration::seconds(1), sched.clock());
+            t.subscribe(Box::new(Collect::new(&mut v)));
+            assert!(t.try_next());
+            clock.advance(Duration::milliseconds(999));
+            assert!(t.try_next());
+            clock.advance(Duration::milliseconds(1));
+            assert!(!t.try_next());
+        }
+        assert_eq!(*v, vec![0]);
+    }
+
+    #[test]
+    fn timer_waits_for_demand() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut t = Timer::<(), _>::with_clock(Duration::seconds(1), sched.clock());
+            t.subscribe(Box::new(Collect::with_demand(&mut v, 0)));
+            clock.advance(Duration::seconds(5));
+            assert!(t.try_next());
+        }
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn negative_delays_are_taken_as_zero() {
+        let sched = VirtualScheduler::new();
+        let mut timer = Box::new(Vec::new());
+        let mut interval = Box::new(Vec::new());
+        {
+            let mut t = Timer::<(), _>::with_clock(Duration::seconds(-1), sched.clock());
+            t.subscribe(Box::new(Collect::new(&mut timer)));
+            assert!(!t.try_next());
+
+            let mut iv = Interval::<(), _>::with_clock(Duration::seconds(-1), sched.clock());
+            iv.subscribe(Box::new(Collect::with_demand(&mut interval, 2)));
+            iv.try_next();
+            iv.try_next();
+        }
+        assert_eq!(*timer, vec![0]);
+        assert_eq!(*interval, vec![0, 1]);
+        assert_eq!(sched.now_ns(), 0);
+    }
+}
@@ -3,16 +3,151 @@
 // This software may be modified and distributed under the terms
 // of the MIT license.  See the LICENSE file for details.
 
+use std::sync::{Arc, Mutex, Condvar};
+use std::sync::atomic::{AtomicUsize, AtomicBool, Ordering};
+use std::time::Duration;
+use std::num::Int;
+use std::usize;
+use std::fmt::Debug;
 
+/// Requesting this many items removes all pushback from a Publisher
+pub const UNBOUNDED : usize = usize::MAX;
+
+/// The longest Subscription::wait blocks for
+const WAIT_MS : i64 = 1;
+
+/// Subscription
+/// The handle a Publisher passes to its Subscriber via on_subscribe.
+/// The Subscriber uses it to tell the Publisher how many more items
+/// it is willing to accept, or to tell it to stop altogether.
+/// Clones share the same demand and cancellation state, so a processor
+/// may hand its upstream subscription straight on to its own subscriber
+#[derive(Clone)]
+pub struct Subscription {
+    index: usize,
+    inner: Arc<SubscriptionState>
+}
+
+struct SubscriptionState {
+    demand: AtomicUsize,
+    cancelled: AtomicBool,
+    lock: Mutex<()>,
+    wakeup: Condvar
+}
+
+impl Subscription {
+
+    pub fn new(index: usize) -> Subscription {
+        Subscription {
+            index: index,
+            inner: Arc::new(SubscriptionState {
+                demand: AtomicUsize::new(0),
+                cancelled: AtomicBool::new(false),
+                lock: Mutex::new(()),
+                wakeup: Condvar::new()
+            })
+        }
+    }
+
+    /// The index of the subscriber, as assigned by its Publisher
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Signal that n more items can be accepted.
+    /// Demand accumulates and saturates at UNBOUNDED
+    pub fn request(&self, n: usize) {
+        loop {
+            let cur = self.inner.demand.load(Ordering::SeqCst);
+            if cur == UNBOUNDED {
+                break
+            }
+            let new = cur.saturating_add(n);
+            if self.inner.demand.compare_and_swap(cur, new, Ordering::SeqCst) == cur {
+                break
+            }
+        }
+        self.wake();
+    }
+
+    /// The number of items the subscriber is currently willing to accept
+    pub fn demand(&self) -> usize {
+        self.inner.demand.load(Ordering::SeqCst)
+    }
+
+    /// Used by a Publisher before each on_next, consumes one unit of demand.
+    /// Returns false if there is none, or the subscription was cancelled
+    pub fn take(&self) -> bool {
+        loop {
+            if self.is_cancelled() {
+                return false
+            }
+            let cur = self.inner.demand.load(Ordering::SeqCst);
+            if cur == 0 {
+                return false
+            }
+            if cur == UNBOUNDED {
+                return true
+            }
+            if self.inner.demand.compare_and_swap(cur, cur - 1, Ordering::SeqCst) == cur {
+                return true
+            }
+        }
+    }
+
+    /// Tell the Publisher to stop sending items
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.wake();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Used by a blocking next which has nothing it may send, rather than spin.
+    /// Blocks until more is requested or the subscription is cancelled, though
+    /// never for longer than WAIT_MS, so the Publisher can still look out for
+    /// the end of its stream, which it passes on whether asked for or not
+    pub fn wait(&self) {
+        let guard = self.inner.lock.lock().unwrap();
+        if self.demand() == 0 && !self.is_cancelled() {
+            let _ = self.inner.wakeup.wait_timeout(guard, Duration::milliseconds(WAIT_MS));
+        }
+    }
+
+    fn wake(&self) {
+        let _guard = self.inner.lock.lock().unwrap();
+        self.inner.wakeup.notify_all();
+    }
+}
+
+/// Notification
+/// Any one of the signals a Subscriber can be sent, as a value.
+/// Materialize turns a stream into these, and Dematerialize turns them back,
+/// so the whole of a stream, its end included, can go wherever an item can
+#[derive(Debug, Clone, PartialEq)]
+pub enum Notification<T, E> {
+    Next(T),
+    Error(E),
+    Complete
+}
 
 pub trait Subscriber {
     type Input;
+    /// The kind of failure this subscriber can be told about,
+    /// processors pass it on to their own subscriber unchanged
+    type Error : Debug;
 
     fn on_next(&mut self, t: Self::Input) -> bool;
-    fn on_subscribe(&mut self, usize) {
+
+    /// Receives the Subscription from the Publisher. Unless overridden
+    /// the subscriber requests everything the publisher has to offer
+    fn on_subscribe(&mut self, sub: Subscription) {
         debug!("on_subscribe called");
+        sub.request(UNBOUNDED);
     }
-    fn on_error(&mut self, err: &str) {
+    fn on_error(&mut self, err: Self::Error) {
         error!("on_error called: {:?}", err);
     }
     fn on_complete(&mut self, force: bool) {
@@ -22,8 +157,9 @@ pub trait Subscriber {
 
 pub trait Publisher<'a> {
     type Output;
+    type Error;
 
-    fn subscribe(&mut self, Box<Subscriber<Input=Self::Output> + 'a>);
+    fn subscribe(&mut self, Box<Subscriber<Input=Self::Output, Error=Self::Error> + 'a>);
 
     /// The basic message event generation function
     /// this is typically called in a loop
@@ -16,7 +16,8 @@ use mio::{
     IoAcceptor,
     Buf, MutBuf,
     Timeout,
-    MioResult};
+    MioResult,
+    MioError};
 
 pub use mio::Token;
 use mio::net::{SockAddr, Socket};
@@ -29,14 +30,15 @@ use iobuf::{Iobuf, RWIobuf, AROIobuf, Allocator, AppendBuf};
 use std::old_io::net::addrinfo::get_host_addresses;
 use std::result::Result;
 use std::sync::Arc;
-use std::sync::mpsc::{Receiver,SyncSender, sync_channel};
+use std::sync::mpsc::{SyncSender, sync_channel};
 
 use std::time::Duration;
 
 use collections::dlist::DList;
 
-use reactive::Subscriber;
+use reactive::{Subscriber, Publisher};
 use protocol::Protocol;
+use error::NetError;
 
 /// The basic sendable buffer which also contains
 /// its own addressing. When the buffer is received,
@@ -49,6 +51,10 @@ pub struct StreamBuf (pub AROIobuf, pub Token);
 #[derive(Show)]
 pub struct ProtoMsg<T> (pub T, pub Token);
 
+/// What the NetEngine sends to the receiving side of a stream,
+/// either a decoded message or the reason the socket failed
+pub type NetResult<T> = Result<ProtoMsg<T>, NetError>;
+
 unsafe impl Send for StreamBuf {}
 
 impl Clone for StreamBuf {
@@ -106,7 +112,7 @@ where T : Protocol, <T as Protocol>::Output : Send
         sock: TcpSocket,
         outbuf: DList<StreamBuf>,
         interest: event::Interest,
-        conn_tx: SyncSender<ProtoMsg<<T as Protocol>::Output>>,
+        conn_tx: SyncSender<NetResult<<T as Protocol>::Output>>,
         marker: u32,
         proto: T,
         buf: ReadBuf
@@ -115,7 +121,7 @@ where T : Protocol, <T as Protocol>::Output : Send
 impl<T> Connection<T>
 where T : Protocol, <T as Protocol>::Output : Send
 {
-    pub fn new(s: TcpSocket, tx: SyncSender<ProtoMsg<<T as Protocol>::Output>>, rbuf: ReadBuf) -> Connection<T> {
+    pub fn new(s: TcpSocket, tx: SyncSender<NetResult<<T as Protocol>::Output>>, rbuf: ReadBuf) -> Connection<T> {
         Connection {
             sock: s,
             outbuf: DList::new(),
@@ -148,7 +154,11 @@ where T : Protocol, <T as Protocol>::Output : Send
                     debug!("Got Writable event for socket, but failed to write any bytes");
                     writable = false;
                 },
-                Err(e) => { error!("error writing to socket: {:?}", e); writable = false }
+                Err(e) => {
+                    debug!("error writing to socket: {:?}", e);
+                    self.report(Err(NetError::Write(e)));
+                    writable = false
+                }
             }
         }
         self.outbuf.len()
@@ -157,6 +167,15 @@ where T : Protocol, <T as Protocol>::Output : Send
     fn read(&mut self) -> MioResult<NonBlock<usize>> {
         self.sock.read(&mut self.buf)
     }
+
+    /// Hands a message or failure to the NetStream reading this connection,
+    /// false if that stream has been dropped and nobody is left to tell
+    fn report(&self, msg: NetResult<<T as Protocol>::Output>) -> bool {
+        match self.conn_tx.send(msg) {
+            Ok(()) => true,
+            Err(..) => { error!("The stream reading this connection was dropped"); false }
+        }
+    }
 }
 
 
@@ -231,9 +250,11 @@ where T : Protocol, <T as Protocol>::Output : Send
     /// all datagrams that arrive will be put into StreamBufs with their
     /// corresponding token, and added to the default outbound data queue
     /// this can be called multiple times for different ips/ports
+    /// The returned stream carries the messages and errors of every
+    /// connection accepted by this listener
     pub fn listen<'b>(&mut self,
                   addr: &'b str,
-                  port: usize) -> Result<Receiver<ProtoMsg<<T as Protocol>::Output>>, String> {
+                  port: usize) -> Result<NetStream<'b, <T as Protocol>::Output>, String> {
         self.inner.listen(addr, port, &mut self.event_loop)
     }
 
@@ -247,7 +268,25 @@ where T : Protocol, <T as Protocol>::Output : Send
     /// poller, but it could be shorted depending on how many events are
     /// occurring
     pub fn timeout(&mut self, timeout: Duration, callback: Box<TimerCB<'a>>) {
-        let tok = self.inner.timeouts.insert((callback, None)).map_err(|_|()).unwrap();
+        self.add_timeout(timeout, None, callback);
+    }
+
+    /// Set a callback to be executed by the event loop every period,
+    /// for as long as it keeps returning true.
+    /// The same resolution caveats apply as for timeout
+    pub fn interval(&mut self, period: Duration, callback: Box<TimerCB<'a>>) {
+        self.add_timeout(period, Some(period), callback);
+    }
+
+    /// Pulls on the publisher every period from within the event loop,
+    /// so a time driven publisher such as Interval can share the reactor's
+    /// thread. It is polled with try_next, and dropped once that returns false
+    pub fn drive<'b, P>(&mut self, period: Duration, mut publisher: P) where P : Publisher<'b> + 'a {
+        self.interval(period, Box::new(move |&mut: _: &mut Reactor| publisher.try_next()));
+    }
+
+    fn add_timeout(&mut self, timeout: Duration, period: Option<Duration>, callback: Box<TimerCB<'a>>) {
+        let tok = self.inner.timeouts.insert((callback, None, period)).map_err(|_|()).unwrap();
         let handle = self.event_loop.timeout(tok, timeout).unwrap();
         self.inner.timeouts.get_mut(tok).unwrap().1 = Some(handle);
     }
@@ -272,8 +311,8 @@ where T : Protocol, <T as Protocol>::Output : Send
 struct EngineInner<'a, T>
 where T : Protocol, <T as Protocol>::Output : Send
 {
-    listeners: Slab<(TcpAcceptor, SyncSender<ProtoMsg<<T as Protocol>::Output>>)>,
-    timeouts: Slab<(Box<TimerCB<'a>>, Option<Timeout>)>,
+    listeners: Slab<(TcpAcceptor, SyncSender<NetResult<<T as Protocol>::Output>>)>,
+    timeouts: Slab<(Box<TimerCB<'a>>, Option<Timeout>, Option<Duration>)>,
     conns: Slab<Connection<T>>,
     config: NetEngineConfig,
 }
@@ -324,7 +363,7 @@ where T : Protocol, <T as Protocol>::Output : Send
     pub fn listen<'b>(&mut self,
                   addr: &'b str,
                   port: usize,
-                  event_loop: &mut Reactor) -> Result<Receiver< ProtoMsg< <T as Protocol>::Output >>, String>
+                  event_loop: &mut Reactor) -> Result<NetStream<'b, <T as Protocol>::Output>, String>
     {
         let ip = get_host_addresses(addr).unwrap()[0];
         match TcpSocket::v4() {
@@ -334,6 +373,7 @@ where T : Protocol, <T as Protocol>::Output : Send
                 Ok(l) => match l.listen(255) {
                     Ok(a) => {
                         let (tx, rx) = sync_channel(self.config.queue_size);
+                        let dtx = event_loop.channel();
                         match self.listeners.insert((a, tx)) {
                             Ok(token) => {
                                 event_loop.register_opt(&self.listeners.get_mut(token).unwrap().0,
@@ -341,7 +381,7 @@ where T : Protocol, <T as Protocol>::Output : Send
                                                         event::READABLE,
                                                         event::PollOpt::edge()).
                                                             map_err(|e| format!("event registration failed: {:?}", e)).
-                                                            map(move |_| rx)
+                                                            map(move |_| NetStream::new(token, rx, dtx))
                             },
                             Err(_) => Err(format!("failed to insert into listener slab"))
                         }
@@ -367,21 +407,35 @@ where T : Protocol, <T as Protocol>::Output : Send
         if self.listeners.contains(token) {
             let calloc = &self.config.allocator;
             let buf_sz = self.config.read_buf_sz;
-            let (ref mut list, ref tx) = *self.listeners.get_mut(token).unwrap();
-                match list.accept() {
-                    Ok(NonBlock::Ready(sock)) => {
-                        let buf = new_buf(buf_sz, calloc.clone());
-                        match self.conns.insert(Connection::new(sock, tx.clone(), buf)) {
-                            Ok(tok) =>  {
-                                event_loop.register_opt(&self.conns.get(tok).unwrap().sock,
-                                                        tok, event::READABLE | event::HUP,
-                                                        event::PollOpt::edge()).unwrap();
-                                          debug!("readable accepted socket for token {:?}", tok); }
-                            Err(..)  => error!("Failed to insert into Slab")
-                        }; },
-                    e => { error!("Failed to accept socket: {:?}", e);}
+            {
+                let (ref mut list, ref tx) = *self.listeners.get_mut(token).unwrap();
+                    match list.accept() {
+                        Ok(NonBlock::Ready(sock)) => {
+                            let buf = new_buf(buf_sz, calloc.clone());
+                            match self.conns.insert(Connection::new(sock, tx.clone(), buf)) {
+                                Ok(tok) =>  {
+                                    event_loop.register_opt(&self.conns.get(tok).unwrap().sock,
+                                                            tok, event::READABLE | event::HUP,
+                                                            event::PollOpt::edge()).unwrap();
+                                              debug!("readable accepted socket for token {:?}", tok); }
+                                Err(..)  => error!("Failed to insert into Slab")
+                            }; },
+                        Ok(NonBlock::WouldBlock) => debug!("Got Readable event for listener, but there was nothing to accept"),
+                        Err(e) => {
+                            debug!("Failed to accept socket: {:?}", e);
+                            if tx.send(Err(NetError::Accept(e))).is_err() {
+                                error!("The stream for listener {:?} was dropped, closing it", token);
+                                close = true;
+                            }
+                        }
+                    }
+                if !close {
+                    event_loop.reregister(list, token, event::READABLE, event::PollOpt::edge()).unwrap();
                 }
-            event_loop.reregister(list, token, event::READABLE, event::PollOpt::edge()).unwrap();
+            }
+            if close {
+                self.listeners.remove(token);
+            }
             return;
 
         } else {
@@ -398,7 +452,7 @@ where T : Protocol, <T as Protocol>::Output : Send
                                 match c.proto.append(&abuf) {
                                     None => {break},
                                     Some((item, remaining, consumed)) => {
-                                        c.conn_tx.send( ProtoMsg(item, token));
+                                        if !c.report(Ok(ProtoMsg(item, token))) { close = true; break }
                                         abuf = remaining;
                                         c.marker += consumed;
                                     }
@@ -418,10 +472,13 @@ where T : Protocol, <T as Protocol>::Output : Send
                         Ok(NonBlock::WouldBlock) => {
                             debug!("Got Readable event for socket, but failed to write any bytes");
                         },
-                        Err(e) => error!("error reading from socket: {:?}", e)
+                        Err(e) => {
+                            debug!("error reading from socket: {:?}", e);
+                            if !c.report(Err(NetError::Read(e))) { close = true }
+                        }
                     };
 
-                    if hint.contains(event::HUPHINT) {
+                    if close || hint.contains(event::HUPHINT) {
                         close = true;
                     }
                     else {
@@ -463,9 +520,16 @@ where T : Protocol, <T as Protocol>::Output : Send
     }
 
     fn timeout(&mut self, event_loop: &mut Reactor, tok: Token) {
-        let (ref mut cb, ref handle) = *self.timeouts.get_mut(tok).unwrap();
-        if !(*cb).call_mut((event_loop,)) {
-            event_loop.clear_timeout(handle.unwrap());
+        let rearm = {
+            let (ref mut cb, _, period) = *self.timeouts.get_mut(tok).unwrap();
+            let again = (*cb).call_mut((event_loop,));
+            if again { period } else { None }
+        };
+        match rearm {
+            // an interval is re-armed for as long as its callback wants
+            Some(p) => self.timeouts.get_mut(tok).unwrap().1 = Some(event_loop.timeout(tok, p).unwrap()),
+            // otherwise it has fired for the last time, and its slot is freed
+            None => { self.timeouts.remove(tok); }
         }
     }
 }
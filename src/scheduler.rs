@@ -3,8 +3,16 @@
 use std::time::duration::Duration;
 use std::old_io::timer::Timer;
 use std::num::Int;
+use std::cmp;
+use std::thunk::Thunk;
+use std::thread::Thread;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Sender, Receiver, TryRecvError};
+use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::fmt::Debug;
 use time::precise_time_ns;
-use reactive::Publisher;
+use reactive::{Publisher, Subscriber, Subscription, Notification, UNBOUNDED};
 use reactor::StreamBuf;
 use libc::{timespec, nanosleep};
 
@@ -34,3 +42,693 @@ pub fn fixed_loop<F>(rate: u64, mut f: F) where F : FnMut() -> bool {
     debug!("Done with loop");
 }
 
+
+/// How a piece of scheduled work should run its loop
+#[derive(Copy, Clone, Debug)]
+pub enum Pacing {
+    /// As fast as it will go
+    Free,
+    /// At most this many times a second, see fixed_loop
+    Rate(u64)
+}
+
+impl Pacing {
+    /// Calls f until it returns false
+    pub fn run<F>(self, mut f: F) where F : FnMut() -> bool {
+        match self {
+            Pacing::Free => while f() {},
+            Pacing::Rate(rate) => fixed_loop(rate, f)
+        }
+    }
+}
+
+/// A piece of work for a scheduler, it is told how to pace its loop
+pub type Work = Thunk<'static, Pacing, ()>;
+
+/// Scheduler
+/// Decides where, and how quickly, a piece of work is run
+pub trait Scheduler {
+    fn schedule(&self, work: Work);
+
+    /// Whether the work is run on the thread which scheduled it,
+    /// as with Immediate and VirtualScheduler, rather than one of its own
+    fn on_calling_thread(&self) -> bool {
+        false
+    }
+}
+
+/// Runs the work on the calling thread, before schedule returns
+#[derive(Copy, Clone)]
+pub struct Immediate;
+
+impl Scheduler for Immediate {
+    fn schedule(&self, work: Work) {
+        work.invoke(Pacing::Free);
+    }
+
+    fn on_calling_thread(&self) -> bool {
+        true
+    }
+}
+
+/// Runs each piece of work on a thread of its own
+#[derive(Copy, Clone)]
+pub struct NewThread;
+
+impl Scheduler for NewThread {
+    fn schedule(&self, work: Work) {
+        Thread::spawn(move || work.invoke(Pacing::Free));
+    }
+}
+
+/// Runs the work on a fixed number of threads. Each thread takes on
+/// one piece of work at a time, anything more waits its turn.
+/// The threads finish up once the pool is dropped and the work runs out
+pub struct ThreadPool {
+    work_tx: Sender<Work>
+}
+
+impl ThreadPool {
+    pub fn new(size: usize) -> ThreadPool {
+        let (tx, rx) = channel::<Work>();
+        let rx = Arc::new(Mutex::new(rx));
+        for _ in range(0, size) {
+            let rx = rx.clone();
+            Thread::spawn(move || {
+                loop {
+                    let work = match rx.lock().unwrap().recv() {
+                        Ok(w) => w,
+                        Err(..) => break
+                    };
+                    work.invoke(Pacing::Free);
+                }
+            });
+        }
+        ThreadPool {
+            work_tx: tx
+        }
+    }
+}
+
+impl Scheduler for ThreadPool {
+    fn schedule(&self, work: Work) {
+        if let Err(..) = self.work_tx.send(work) {
+            error!("The thread pool has no threads left to run the work");
+        }
+    }
+}
+
+/// Runs each piece of work on a thread of its own,
+/// with its loop held to a fixed rate by fixed_loop
+#[derive(Copy, Clone)]
+pub struct FixedRate {
+    rate: u64
+}
+
+impl FixedRate {
+    pub fn new(rate: u64) -> FixedRate {
+        FixedRate {
+            rate: rate
+        }
+    }
+}
+
+impl Scheduler for FixedRate {
+    fn schedule(&self, work: Work) {
+        let rate = self.rate;
+        Thread::spawn(move || work.invoke(Pacing::Rate(rate)));
+    }
+}
+
+// What the two halves of a thread crossing share, so that what is asked
+// for on the receiving side, and its cancellation, make it back to the
+// sending side. Either side may top up what the upstream has been asked for
+struct Crossing {
+    upstream: Option<Subscription>,
+    downstream: Option<Subscription>,
+    // all the upstream has been asked for, and all passed on downstream
+    asked: usize,
+    passed: usize
+}
+
+impl Crossing {
+    fn new() -> Arc<Mutex<Crossing>> {
+        Arc::new(Mutex::new(Crossing {
+            upstream: None,
+            downstream: None,
+            asked: 0,
+            passed: 0
+        }))
+    }
+
+    /// Asks the upstream for whatever the downstream has asked for, which
+    /// has neither been asked for already nor passed on. Nothing is over
+    /// asked for, as the downstream's demand is used up before passed grows
+    fn top_up(&mut self) {
+        let (up, down) = match (self.upstream.as_ref(), self.downstream.as_ref()) {
+            (Some(up), Some(down)) => (up, down),
+            _ => return
+        };
+        if down.is_cancelled() {
+            up.cancel();
+            return
+        }
+        if self.asked == UNBOUNDED {
+            return
+        }
+        let demand = down.demand();
+        if demand == UNBOUNDED {
+            up.request(UNBOUNDED);
+            self.asked = UNBOUNDED;
+            return
+        }
+        let wanted = demand.saturating_add(self.passed);
+        if wanted > self.asked {
+            up.request(wanted - self.asked);
+            self.asked = wanted;
+        }
+    }
+
+    fn cancel(&mut self) {
+        if let Some(up) = self.upstream.as_ref() { up.cancel(); }
+    }
+}
+
+/// Outbound
+/// The sending half of a thread crossing, unlike Decoupler
+/// errors and completion cross over as well as the items.
+/// It asks its upstream for only as much as the receiving half has
+/// been asked for, and cancels it once the receiving half goes away
+pub struct Outbound<O, E> where O : Send, E : Send {
+    tx: Sender<Notification<O, E>>,
+    crossing: Arc<Mutex<Crossing>>
+}
+
+impl<O, E> Subscriber for Outbound<O, E> where O : Send, E : Send + Debug {
+    type Input = O;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        let mut crossing = self.crossing.lock().unwrap();
+        crossing.upstream = Some(sub);
+        crossing.top_up();
+    }
+
+    fn on_next(&mut self, t: O) -> bool {
+        match self.tx.send(Notification::Next(t)) {
+            Ok(()) => { self.crossing.lock().unwrap().top_up(); true },
+            Err(..) => {
+                // nobody is listening on the other side
+                self.crossing.lock().unwrap().cancel();
+                false
+            }
+        }
+    }
+
+    fn on_error(&mut self, err: E) {
+        let _ = self.tx.send(Notification::Error(err));
+    }
+
+    fn on_complete(&mut self, _: bool) {
+        let _ = self.tx.send(Notification::Complete);
+    }
+}
+
+/// Inbound
+/// The receiving half of a thread crossing. What its subscriber asks
+/// for is carried back across each time it is polled
+pub struct Inbound<'a, O, E> where O : Send, E : Send {
+    rx: Option<Receiver<Notification<O, E>>>,
+    crossing: Arc<Mutex<Crossing>>,
+    pending: Option<O>,
+    subscriber: Option<Box<Subscriber<Input=O, Error=E> + 'a>>,
+    subscription: Subscription
+}
+
+impl<'a, O, E> Inbound<'a, O, E> where O : Send, E : Send {
+    fn new(rx: Receiver<Notification<O, E>>, crossing: Arc<Mutex<Crossing>>) -> Inbound<'a, O, E> {
+        Inbound {
+            rx: Some(rx),
+            crossing: crossing,
+            pending: None,
+            subscriber: None,
+            subscription: Subscription::new(0)
+        }
+    }
+
+    /// Drops the subscriber and the receiver, so the sending side
+    /// learns that nobody is listening anymore
+    fn release(&mut self) -> bool {
+        self.subscription.cancel();
+        self.crossing.lock().unwrap().cancel();
+        self.subscriber = None;
+        self.rx = None;
+        self.pending = None;
+        false
+    }
+
+    /// Passes on what crossed over. An item is only passed on if it
+    /// has been asked for, otherwise it is held back until it is
+    fn deliver(&mut self, sig: Result<Notification<O, E>, ()>) -> bool {
+        let more = match self.subscriber.as_mut() {
+            Some(s) => match sig {
+                Ok(Notification::Next(t)) => if self.subscription.take() {
+                    self.crossing.lock().unwrap().passed += 1;
+                    s.on_next(t)
+                } else {
+                    self.pending = Some(t);
+                    true
+                },
+                Ok(Notification::Error(e)) => { s.on_error(e); false },
+                Ok(Notification::Complete) => { s.on_complete(false); false },
+                Err(..) => { info!("The other side of the crossing went away"); s.on_complete(false); false }
+            },
+            None => { error!("My subscriber went away"); false }
+        };
+        if !more { self.release(); }
+        more
+    }
+}
+
+impl<'a, O, E> Publisher<'a> for Inbound<'a, O, E> where O : Send, E : Send {
+    type Output = O;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=O, Error=E> + 'a>) {
+        self.subscription = Subscription::new(0);
+        self.subscriber = Some(s);
+        self.subscriber.as_mut().unwrap().on_subscribe(self.subscription.clone());
+        let mut crossing = self.crossing.lock().unwrap();
+        crossing.downstream = Some(self.subscription.clone());
+        crossing.top_up();
+    }
+
+    // Only blocks on the crossing once there is demand, without it the crossing
+    // is still looked at, so that an error or completion can be passed on
+    fn next(&mut self) -> bool {
+        if self.subscription.is_cancelled() { return self.release() }
+        self.crossing.lock().unwrap().top_up();
+        let received = match self.rx.as_ref() {
+            Some(rx) if self.pending.is_none() && self.subscription.demand() > 0 => Some(rx.recv().map_err(|_| ())),
+            _ => None
+        };
+        let more = match received {
+            Some(sig) => self.deliver(sig),
+            None => self.try_next()
+        };
+        if more && self.subscription.demand() == 0 { self.subscription.wait(); }
+        more
+    }
+
+    // Does not block
+    fn try_next(&mut self) -> bool {
+        if self.subscription.is_cancelled() { return self.release() }
+        self.crossing.lock().unwrap().top_up();
+        if let Some(t) = self.pending.take() {
+            return self.deliver(Ok(Notification::Next(t)))
+        }
+        let sig = match self.rx.as_ref() {
+            Some(rx) => match rx.try_recv() {
+                Ok(sig) => Ok(sig),
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => Err(())
+            },
+            None => return false
+        };
+        self.deliver(sig)
+    }
+}
+
+/// Runs the upstream part of a chain on the scheduler.
+/// Publishers are not Send, so rather than the publisher itself this takes
+/// a factory, which builds it once the work is running where it belongs.
+/// Its items, errors and completion come out of the returned publisher,
+/// and the upstream is asked for only as much as its subscriber asks for.
+/// Panics if the scheduler runs work on the calling thread, such as Immediate,
+/// as the upstream would be run to its end, if it has one, before this returned
+///
+/// ```ignore
+/// let mut chain = subscribe_on(&NewThread, move || IterPublisher::<_, _, ()>::new(range(0us, 10)).map(|i| i * 2));
+/// chain.subscribe(Box::new(StdoutSubscriber::new()));
+/// chain.run();
+/// ```
+pub fn subscribe_on<'a, S, F, P>(sched: &S, factory: F) -> Inbound<'a, <P as Publisher<'static>>::Output, <P as Publisher<'static>>::Error>
+where S : Scheduler,
+      F : FnOnce() -> P + Send + 'static,
+      P : Publisher<'static>,
+      <P as Publisher<'static>>::Output : Send + 'static,
+      <P as Publisher<'static>>::Error : Send + Debug + 'static
+{
+    if sched.on_calling_thread() {
+        panic!("subscribe_on needs a scheduler which runs work on a thread of its own")
+    }
+    let (tx, rx) = channel();
+    let crossing = Crossing::new();
+    let outbound = Outbound { tx: tx, crossing: crossing.clone() };
+    sched.schedule(Thunk::with_arg(move |pacing: Pacing| {
+        let mut p = factory();
+        p.subscribe(Box::new(outbound));
+        pacing.run(|| p.next());
+    }));
+    Inbound::new(rx, crossing)
+}
+
+/// Runs the downstream part of a chain on the scheduler.
+/// Subscribers are not Send, so this takes a factory which builds the rest
+/// of the chain once the work is running where it belongs. The returned
+/// subscriber is subscribed upstream, and passes everything across,
+/// asking the upstream for as much as the rest of the chain asks for.
+/// Panics if the scheduler runs work on the calling thread, such as Immediate,
+/// as it would wait there for items which could then never be sent
+pub fn observe_on<S, F, O, E>(sched: &S, factory: F) -> Outbound<O, E>
+where S : Scheduler,
+      F : FnOnce() -> Box<Subscriber<Input=O, Error=E> + 'static> + Send + 'static,
+      O : Send + 'static,
+      E : Send + Debug + 'static
+{
+    if sched.on_calling_thread() {
+        panic!("observe_on needs a scheduler which runs work on a thread of its own")
+    }
+    let (tx, rx) = channel();
+    let crossing = Crossing::new();
+    let inbound_crossing = crossing.clone();
+    sched.schedule(Thunk::with_arg(move |pacing: Pacing| {
+        let mut inbound = Inbound::new(rx, inbound_crossing);
+        inbound.subscribe(factory());
+        pacing.run(|| inbound.next());
+    }));
+    Outbound { tx: tx, crossing: crossing }
+}
+
+/// Clock
+/// Where time-aware stages get the time from, so that tests can
+/// swap the real time for a VirtualClock which only moves when told
+pub trait Clock {
+    /// Nanoseconds since some fixed point
+    fn now_ns(&self) -> u64;
+
+    /// Waits until ns nanoseconds have passed
+    fn sleep_ns(&self, ns: u64);
+}
+
+/// The time as told by precise_time_ns
+#[derive(Copy, Clone, Debug)]
+pub struct RealClock;
+
+impl Clock for RealClock {
+    fn now_ns(&self) -> u64 {
+        precise_time_ns()
+    }
+
+    fn sleep_ns(&self, ns: u64) {
+        let ts = timespec { tv_sec: (ns / 1_000_000_000) as i64, tv_nsec: (ns % 1_000_000_000) as i64 };
+        let mut rem = timespec { tv_sec: 0, tv_nsec: 0 };
+        unsafe { nanosleep(&ts as *const timespec, &mut rem as *mut timespec) };
+    }
+}
+
+/// A duration in nanoseconds, where a negative one counts as none at all
+pub fn clamped_ns(d: Duration) -> u64 {
+    cmp::max(d.num_nanoseconds().unwrap(), 0) as u64
+}
+
+/// How long a stage with nothing due sleeps for between polls
+const IDLE_NS : u64 = 1_000_000;
+
+/// Sleeps for a while in next, but not past the time something is due
+pub fn idle<C>(clock: &C, due_ns: Option<u64>) where C : Clock {
+    let now = clock.now_ns();
+    let ns = match due_ns {
+        Some(due) if due <= now => return,
+        Some(due) if due - now < IDLE_NS => due - now,
+        _ => IDLE_NS
+    };
+    clock.sleep_ns(ns);
+}
+
+/// Waits on the subscription for a stage which only has what it holds
+/// back to pass on. With no demand left it waits for more to be asked for,
+/// otherwise it gives the upstream a moment to come up with something
+pub fn idle_on(sub: Option<&Subscription>) {
+    match sub {
+        Some(s) if s.demand() == 0 && !s.is_cancelled() => s.wait(),
+        _ => RealClock.sleep_ns(IDLE_NS)
+    }
+}
+
+/// Tick
+/// A processor with something to do other than as items arrive, such as a
+/// Buffer with a time limit, or a stage holding back items, or its completion,
+/// until they are asked for. Hung off of a publisher with Ticked, it is
+/// ticked each time the publisher is polled, and after the publisher is done
+pub trait Tick {
+    /// Acts on whatever has fallen due by now, or has since been asked for.
+    /// Returns false once there is nothing more for it to do, which is once
+    /// it has passed on its completion or error, or has been cancelled
+    fn tick(&mut self) -> bool;
+
+    /// Waits until something may next fall due, though not for long
+    fn idle(&self);
+}
+
+/// A clock whose time stands still until it is moved on.
+/// Clones share the same time, so a test keeps one and hands
+/// the others to the stages under test
+#[derive(Clone)]
+pub struct VirtualClock {
+    now: Rc<Cell<u64>>
+}
+
+impl VirtualClock {
+    pub fn new() -> VirtualClock {
+        VirtualClock {
+            now: Rc::new(Cell::new(0))
+        }
+    }
+
+    pub fn advance(&self, d: Duration) {
+        self.advance_ns(clamped_ns(d));
+    }
+
+    pub fn advance_ns(&self, ns: u64) {
+        self.now.set(self.now.get() + ns);
+    }
+
+    /// Moves the time to ns, time never goes backwards
+    pub fn set_ns(&self, ns: u64) {
+        if ns > self.now.get() {
+            self.now.set(ns);
+        }
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now_ns(&self) -> u64 {
+        self.now.get()
+    }
+
+    // nobody waits in virtual time, it just moves on
+    fn sleep_ns(&self, ns: u64) {
+        self.advance_ns(ns);
+    }
+}
+
+/// VirtualScheduler
+/// A scheduler for tests, which runs nothing until it is told how much
+/// time has passed. Work is then run on the calling thread in the order
+/// it fell due, with the clock showing the time it was due at
+pub struct VirtualScheduler {
+    clock: VirtualClock,
+    queue: RefCell<Vec<(u64, usize, Work)>>,
+    seq: Cell<usize>
+}
+
+impl VirtualScheduler {
+    pub fn new() -> VirtualScheduler {
+        VirtualScheduler {
+            clock: VirtualClock::new(),
+            queue: RefCell::new(Vec::new()),
+            seq: Cell::new(0)
+        }
+    }
+
+    /// A handle on the scheduler's time, to hand to the stages under test
+    pub fn clock(&self) -> VirtualClock {
+        self.clock.clone()
+    }
+
+    pub fn now_ns(&self) -> u64 {
+        self.clock.now_ns()
+    }
+
+    /// Queues work to run once delay has passed
+    pub fn schedule_after(&self, delay: Duration, work: Work) {
+        let due = self.clock.now_ns() + clamped_ns(delay);
+        let seq = self.seq.get();
+        self.seq.set(seq + 1);
+        self.queue.borrow_mut().push((due, seq, work));
+    }
+
+    /// The number of pieces of work still waiting
+    pub fn pending(&self) -> usize {
+        self.queue.borrow().len()
+    }
+
+    /// Moves time on by d, running everything which falls due on the way
+    pub fn advance(&self, d: Duration) {
+        let until = self.clock.now_ns() + clamped_ns(d);
+        loop {
+            // work may schedule more work, so look again each time round
+            let next = {
+                let queue = self.queue.borrow();
+                let mut next: Option<usize> = None;
+                for (i, &(due, seq, _)) in queue.iter().enumerate() {
+                    if due <= until && next.map_or(true, |n| (due, seq) < (queue[n].0, queue[n].1)) {
+                        next = Some(i);
+                    }
+                }
+                next
+            };
+            match next {
+                Some(i) => {
+                    let (due, _, work) = self.queue.borrow_mut().remove(i);
+                    self.clock.set_ns(due);
+                    work.invoke(Pacing::Free);
+                },
+                None => break
+            }
+        }
+        self.clock.set_ns(until);
+    }
+
+    /// Runs whatever is due now, without moving time on
+    pub fn run_due(&self) {
+        self.advance(Duration::zero());
+    }
+}
+
+impl Scheduler for VirtualScheduler {
+    fn schedule(&self, work: Work) {
+        self.schedule_after(Duration::zero(), work);
+    }
+
+    fn on_calling_thread(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use scheduler::{VirtualScheduler, Scheduler, Pacing, Immediate, NewThread, subscribe_on, observe_on};
+    use publisher::{IterPublisher, PublisherExt};
+    use subscriber::{Collect, Decoupler};
+    use reactive::{Publisher, Subscriber};
+    use std::time::duration::Duration;
+    use std::thunk::Thunk;
+    use std::sync::mpsc::channel;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn virtual_work_runs_in_due_order() {
+        let sched = VirtualScheduler::new();
+        let (tx, rx) = channel();
+        let (tx1, tx2, tx3) = (tx.clone(), tx.clone(), tx);
+        sched.schedule_after(Duration::seconds(10), Thunk::with_arg(move |_: Pacing| { tx1.send(3us).unwrap(); }));
+        sched.schedule_after(Duration::seconds(5), Thunk::with_arg(move |_: Pacing| { tx2.send(2us).unwrap(); }));
+        sched.schedule(Thunk::with_arg(move |_: Pacing| { tx3.send(1us).unwrap(); }));
+
+        sched.advance(Duration::seconds(6));
+        assert_eq!(rx.try_recv().ok(), Some(1));
+        assert_eq!(rx.try_recv().ok(), Some(2));
+        assert!(rx.try_recv().is_err());
+        assert_eq!(sched.pending(), 1);
+        assert_eq!(sched.now_ns(), 6_000_000_000);
+
+        sched.advance(Duration::seconds(4));
+        assert_eq!(rx.try_recv().ok(), Some(3));
+    }
+
+    #[test]
+    fn subscribes_on_another_thread() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = subscribe_on(&NewThread, move || IterPublisher::<_, _, ()>::new(range(0u32, 5)).map(|i| i * 2));
+            chain.subscribe(Box::new(Collect::new(&mut v)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![0, 2, 4, 6, 8]);
+    }
+
+    #[test]
+    fn crossing_completes_when_exactly_drained() {
+        let mut v = Box::new(Vec::new());
+        {
+            // run only returns once the completion has crossed over,
+            // which it does even though nothing more was asked for
+            let mut chain = subscribe_on(&NewThread, move || IterPublisher::<_, _, ()>::new(range(0u32, 3)));
+            chain.subscribe(Box::new(Collect::with_demand(&mut v, 3)));
+            chain.run();
+        }
+        assert_eq!(*v, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn observes_on_another_thread() {
+        let (tx, rx) = channel();
+        {
+            let out = observe_on(&NewThread, move || Box::new(Decoupler::new(tx)) as Box<Subscriber<Input=u32, Error=()>>);
+            let mut p = IterPublisher::new(range(0u32, 5));
+            p.subscribe(Box::new(out));
+            p.run();
+        }
+        // the sender goes once the other thread has seen the stream complete
+        let v: Vec<u32> = rx.iter().collect();
+        assert_eq!(v, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn crossing_carries_demand_back() {
+        let made = Arc::new(AtomicUsize::new(0));
+        let counter = made.clone();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut chain = subscribe_on(&NewThread, move || {
+                IterPublisher::<_, _, ()>::new(range(0u32, 100)).map(move |i| { counter.fetch_add(1, Ordering::SeqCst); i })
+            });
+            chain.subscribe(Box::new(Collect::with_demand(&mut v, 3)));
+            // once the three are in, each of these waits a while for more
+            for _ in range(0, 20) {
+                chain.next();
+            }
+        }
+        assert_eq!(*v, vec![0, 1, 2]);
+        assert_eq!(made.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn virtual_time_never_goes_backwards() {
+        let sched = VirtualScheduler::new();
+        let clock = sched.clock();
+        let (tx, rx) = channel();
+        clock.advance(Duration::seconds(1));
+        clock.advance(Duration::seconds(-5));
+        sched.schedule_after(Duration::seconds(-1), Thunk::with_arg(move |_: Pacing| { tx.send(1us).unwrap(); }));
+        sched.advance(Duration::seconds(-1));
+        assert_eq!(rx.try_recv().ok(), Some(1));
+        assert_eq!(sched.now_ns(), 1_000_000_000);
+    }
+
+    #[test]
+    #[should_fail]
+    fn subscribe_on_rejects_immediate() {
+        subscribe_on(&Immediate, move || IterPublisher::<_, _, ()>::new(range(0u32, 5)));
+    }
+
+    #[test]
+    #[should_fail]
+    fn observe_on_rejects_immediate() {
+        let (tx, _rx) = channel();
+        observe_on(&Immediate, move || Box::new(Decoupler::new(tx)) as Box<Subscriber<Input=u32, Error=()>>);
+    }
+}
@@ -0,0 +1,922 @@
+// Copyright (C) 2015 <Rick Richardson r@12sidedtech.com>
+//
+// This software may be modified and distributed under the terms
+// of the MIT license.  See the LICENSE file for details.
+
+//! Strategies which decide how a stage with several subscribers
+//! shares out its items between them, and how a stage with several
+//! inputs chooses between them. See the README for the modes
+
+use std::collections::RingBuf;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::fmt::Debug;
+use reactive::{Publisher, Subscriber, Subscription};
+use publisherimpl::{Slot, slot_with_capacity};
+
+/// Outlet
+/// One subscriber of a multi-subscriber stage, along with its
+/// subscription and a queue a strategy may keep items in for it.
+/// Whatever is queued goes out as the subscriber asks for it, and is
+/// sent ahead of its completion, or of an error given to error_queued
+pub struct Outlet<'a, I, E> {
+    subscriber: Box<Subscriber<Input=I, Error=E> + 'a>,
+    subscription: Subscription,
+    queue: RingBuf<I>,
+    ending: Option<Result<bool, E>>,
+    closed: bool
+}
+
+impl<'a, I, E> Outlet<'a, I, E> {
+
+    pub fn new(s: Box<Subscriber<Input=I, Error=E> + 'a>, sub: Subscription) -> Outlet<'a, I, E> {
+        Outlet {
+            subscriber: s,
+            subscription: sub,
+            queue: RingBuf::new(),
+            ending: None,
+            closed: false
+        }
+    }
+
+    pub fn subscription(&self) -> &Subscription {
+        &self.subscription
+    }
+
+    /// The subscriber has gone away, or cancelled its subscription
+    pub fn is_closed(&self) -> bool {
+        self.closed || self.subscription.is_cancelled()
+    }
+
+    /// The subscriber is willing to accept another item
+    pub fn has_demand(&self) -> bool {
+        !self.is_closed() && self.subscription.demand() > 0
+    }
+
+    /// How many more items the subscriber has asked for, none once it is closed
+    pub fn demand(&self) -> usize {
+        if self.is_closed() { 0 } else { self.subscription.demand() }
+    }
+
+    /// How many more items the subscriber has asked for,
+    /// beyond those already queued for it
+    pub fn room(&self) -> usize {
+        self.demand().saturating_sub(self.queue.len())
+    }
+
+    /// The stream has ended for the subscriber, but what is
+    /// queued for it is still to be sent ahead of the end
+    pub fn is_ending(&self) -> bool {
+        self.ending.is_some() && !self.is_closed()
+    }
+
+    pub fn queue(&mut self) -> &mut RingBuf<I> {
+        &mut self.queue
+    }
+
+    /// How many items are queued for the subscriber
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Passes an item to the subscriber, using up one unit of its demand.
+    /// Returns false, and closes the outlet, if the subscriber asks to stop
+    pub fn send(&mut self, t: I) -> bool {
+        if self.is_closed() {
+            return false
+        }
+        self.subscription.take();
+        if !self.subscriber.on_next(t) {
+            self.close();
+        }
+        !self.closed
+    }
+
+    /// Sends on as many queued items as the subscriber has demand for,
+    /// then the end of the stream, if it has ended and nothing is left
+    pub fn send_queued(&mut self) {
+        while self.has_demand() {
+            match self.queue.pop_front() {
+                Some(t) => { self.send(t); },
+                None => break
+            }
+        }
+        if self.queue.is_empty() && !self.is_closed() {
+            match self.ending.take() {
+                Some(Ok(force)) => { self.subscriber.on_complete(force); self.closed = true; },
+                Some(Err(err)) => { self.subscriber.on_error(err); self.closed = true; },
+                None => ()
+            }
+        }
+    }
+
+    pub fn close(&mut self) {
+        self.closed = true;
+        self.subscription.cancel();
+        self.queue.clear();
+    }
+
+    /// Passes on the error straight away, dropping anything still queued
+    pub fn error(&mut self, err: E) {
+        if !self.is_closed() {
+            self.subscriber.on_error(err);
+        }
+        self.closed = true;
+        self.queue.clear();
+    }
+
+    /// Passes on the error once everything queued ahead of it has been sent
+    pub fn error_queued(&mut self, err: E) {
+        if !self.is_closed() {
+            self.ending = Some(Err(err));
+            self.send_queued();
+        }
+    }
+
+    /// Completes the subscriber once everything queued has been sent
+    pub fn complete(&mut self, force: bool) {
+        if !self.is_closed() {
+            self.ending = Some(Ok(force));
+            self.send_queued();
+        }
+    }
+}
+
+/// OutputStrategy
+/// Decides which of a stage's subscribers are handed each item,
+/// and what happens to items which a subscriber has no demand for
+pub trait OutputStrategy<I> {
+
+    /// Shares out a newly arrived item among the outlets
+    fn push<'a, E>(&mut self, t: I, outlets: &mut [Outlet<'a, I, E>]);
+
+    /// Sends on anything held back, to the outlets which have since asked for more
+    fn flush<'a, E>(&mut self, outlets: &mut [Outlet<'a, I, E>]);
+
+    /// The stream is over, so anything still held back is moved into the
+    /// queues of the outlets it is meant for. From there it goes out as
+    /// each of them asks for it, ahead of their completion
+    fn drain<'a, E>(&mut self, outlets: &mut [Outlet<'a, I, E>]);
+
+    /// How many more items the outlets can take between them,
+    /// which is as much as the stage asks its upstream for
+    fn demand<'a, E>(&self, outlets: &[Outlet<'a, I, E>]) -> usize;
+}
+
+/// LockStep
+/// Keeps all subscribers in sync. An item is sent to all of them
+/// at once, and only when every one of them has demand for it.
+/// Until then it waits in a queue shared by all. An item which
+/// arrives while nobody is subscribed is dropped
+pub struct LockStep<I> {
+    queue: RingBuf<I>
+}
+
+impl<I> LockStep<I> {
+    pub fn new() -> LockStep<I> {
+        LockStep {
+            queue: RingBuf::new()
+        }
+    }
+}
+
+impl<I> OutputStrategy<I> for LockStep<I> where I : Clone {
+
+    fn push<'a, E>(&mut self, t: I, outlets: &mut [Outlet<'a, I, E>]) {
+        self.queue.push_back(t);
+        self.flush(outlets);
+    }
+
+    fn flush<'a, E>(&mut self, outlets: &mut [Outlet<'a, I, E>]) {
+        // holding on to it would only hand it to whoever subscribes next
+        if outlets.iter().all(|o| o.is_closed()) {
+            self.queue.clear();
+            return
+        }
+        while !self.queue.is_empty() {
+            let ready = outlets.iter().filter(|o| !o.is_closed()).all(|o| o.has_demand());
+            if !ready {
+                break
+            }
+            let t = self.queue.pop_front().unwrap();
+            for o in outlets.iter_mut() {
+                o.send(t.clone());
+            }
+        }
+    }
+
+    fn drain<'a, E>(&mut self, outlets: &mut [Outlet<'a, I, E>]) {
+        while let Some(t) = self.queue.pop_front() {
+            for o in outlets.iter_mut().filter(|o| !o.is_closed()) {
+                o.queue().push_back(t.clone());
+            }
+        }
+    }
+
+    /// The least any open outlet has room for, less what is already queued
+    fn demand<'a, E>(&self, outlets: &[Outlet<'a, I, E>]) -> usize {
+        let least = outlets.iter().filter(|o| !o.is_closed()).map(|o| o.room()).min().unwrap_or(0);
+        least.saturating_sub(self.queue.len())
+    }
+}
+
+/// Eager
+/// Sends each item straight away to every subscriber with demand for it.
+/// Subscribers without demand never see it
+#[derive(Copy, Clone)]
+pub struct Eager;
+
+impl<I> OutputStrategy<I> for Eager where I : Clone {
+
+    fn push<'a, E>(&mut self, t: I, outlets: &mut [Outlet<'a, I, E>]) {
+        for o in outlets.iter_mut() {
+            if o.has_demand() {
+                o.send(t.clone());
+            }
+        }
+    }
+
+    fn flush<'a, E>(&mut self, _: &mut [Outlet<'a, I, E>]) { }
+
+    fn drain<'a, E>(&mut self, _: &mut [Outlet<'a, I, E>]) { }
+
+    /// The most any outlet has room for, the rest miss out
+    fn demand<'a, E>(&self, outlets: &[Outlet<'a, I, E>]) -> usize {
+        outlets.iter().map(|o| o.room()).max().unwrap_or(0)
+    }
+}
+
+/// RoundRobin
+/// Each item goes to just one subscriber, taking turns among those with demand.
+/// When none of them has demand the item waits in a shared queue,
+/// though while nobody is subscribed it is dropped.
+/// Items are never cloned, which suits spreading work across workers
+pub struct RoundRobin<I> {
+    queue: RingBuf<I>,
+    turn: usize
+}
+
+impl<I> RoundRobin<I> {
+    pub fn new() -> RoundRobin<I> {
+        RoundRobin {
+            queue: RingBuf::new(),
+            turn: 0
+        }
+    }
+
+    /// The next outlet in turn which is willing to take an item
+    fn next_ready<'a, E>(&mut self, outlets: &[Outlet<'a, I, E>], any: bool) -> Option<usize> {
+        let n = outlets.len();
+        for k in range(0, n) {
+            let i = (self.turn + k) % n;
+            if outlets[i].has_demand() || (any && !outlets[i].is_closed()) {
+                self.turn = i + 1;
+                return Some(i)
+            }
+        }
+        None
+    }
+}
+
+impl<I> OutputStrategy<I> for RoundRobin<I> {
+
+    fn push<'a, E>(&mut self, t: I, outlets: &mut [Outlet<'a, I, E>]) {
+        self.queue.push_back(t);
+        self.flush(outlets);
+    }
+
+    fn flush<'a, E>(&mut self, outlets: &mut [Outlet<'a, I, E>]) {
+        if outlets.iter().all(|o| o.is_closed()) {
+            self.queue.clear();
+            return
+        }
+        while !self.queue.is_empty() {
+            match self.next_ready(outlets, false) {
+                Some(i) => { let t = self.queue.pop_front().unwrap(); outlets[i].send(t); },
+                None => break
+            }
+        }
+    }
+
+    fn drain<'a, E>(&mut self, outlets: &mut [Outlet<'a, I, E>]) {
+        while !self.queue.is_empty() {
+            match self.next_ready(outlets, true) {
+                Some(i) => { let t = self.queue.pop_front().unwrap(); outlets[i].queue().push_back(t); },
+                None => { self.queue.clear(); break }
+            }
+        }
+    }
+
+    /// What the outlets have room for between them, less what is already queued
+    fn demand<'a, E>(&self, outlets: &[Outlet<'a, I, E>]) -> usize {
+        let total = outlets.iter().fold(0, |n, o| n.saturating_add(o.room()));
+        total.saturating_sub(self.queue.len())
+    }
+}
+
+/// PerSubscriberQueue
+/// Every subscriber is sent every item. A subscriber without demand
+/// has the item queued for it alone, and gets it once it asks for more,
+/// so a slow subscriber holds up nobody but itself
+#[derive(Copy, Clone)]
+pub struct PerSubscriberQueue;
+
+impl<I> OutputStrategy<I> for PerSubscriberQueue where I : Clone {
+
+    fn push<'a, E>(&mut self, t: I, outlets: &mut [Outlet<'a, I, E>]) {
+        for o in outlets.iter_mut() {
+            o.send_queued();
+            if o.is_closed() {
+                continue
+            }
+            if o.has_demand() && o.queue().is_empty() {
+                o.send(t.clone());
+            } else {
+                o.queue().push_back(t.clone());
+            }
+        }
+    }
+
+    fn flush<'a, E>(&mut self, outlets: &mut [Outlet<'a, I, E>]) {
+        for o in outlets.iter_mut() {
+            o.send_queued();
+        }
+    }
+
+    // what is held back is already in the outlets' own queues
+    fn drain<'a, E>(&mut self, _: &mut [Outlet<'a, I, E>]) { }
+
+    /// Enough for the outlet with the most room, a slower one has the rest queued
+    fn demand<'a, E>(&self, outlets: &[Outlet<'a, I, E>]) -> usize {
+        outlets.iter().map(|o| o.room()).max().unwrap_or(0)
+    }
+}
+
+/// Inlet
+/// One upstream publisher of a multi-input stage, along with the
+/// slot its items are left in until a strategy picks them
+pub struct Inlet<'a, T, E> {
+    publisher: Box<Publisher<'a, Output=T, Error=E> + 'a>,
+    slot: Rc<RefCell<Slot<T, E>>>,
+    arrived: u64
+}
+
+impl<'a, T, E> Inlet<'a, T, E> where T : 'a, E : Debug + 'a {
+
+    pub fn new<P>(mut p: P) -> Inlet<'a, T, E> where P : Publisher<'a, Output=T, Error=E> + 'a {
+        let slot = slot_with_capacity(&mut p, 1);
+        let p: Box<Publisher<'a, Output=T, Error=E> + 'a> = Box::new(p);
+        Inlet {
+            publisher: p,
+            slot: slot,
+            arrived: 0
+        }
+    }
+}
+
+impl<'a, T, E> Inlet<'a, T, E> {
+
+    /// Gives the publisher a chance to produce, without blocking,
+    /// unless there is already an item waiting. The stamp records
+    /// when the waiting item turned up, for strategies which care
+    pub fn poll(&mut self, stamp: u64) {
+        if !self.slot.borrow().is_empty() || self.slot.borrow().is_done() {
+            return
+        }
+        if !self.publisher.try_next() {
+            self.slot.borrow_mut().set_done();
+        }
+        if !self.slot.borrow().is_empty() {
+            self.arrived = stamp;
+        }
+    }
+
+    /// An item is waiting to be taken
+    pub fn is_ready(&self) -> bool {
+        !self.slot.borrow().is_empty()
+    }
+
+    /// When the waiting item turned up
+    pub fn arrived(&self) -> u64 {
+        self.arrived
+    }
+
+    /// The publisher is done, and everything it gave has been taken
+    pub fn is_drained(&self) -> bool {
+        self.slot.borrow().is_drained()
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.slot.borrow_mut().pop()
+    }
+
+    pub fn take_error(&mut self) -> Option<E> {
+        self.slot.borrow_mut().take_error()
+    }
+
+    /// Tells the publisher to stop
+    pub fn cancel(&mut self) {
+        self.slot.borrow_mut().cancel();
+    }
+}
+
+/// InputStrategy
+/// Decides which of a stage's inputs the next item is taken from
+pub trait InputStrategy {
+
+    /// Polls the inputs as it sees fit, and returns the index of the one
+    /// to take the next item from, or None if nothing is ready yet.
+    /// The stamp only ever goes up, and is passed on to Inlet::poll
+    fn select<'a, T, E>(&mut self, inputs: &mut [Inlet<'a, T, E>], stamp: u64) -> Option<usize>;
+}
+
+/// RoundRobinInput
+/// The inputs take turns, each passing on at most one item per turn,
+/// so a busy input cannot starve a quiet one
+#[derive(Copy, Clone)]
+pub struct RoundRobinInput {
+    turn: usize
+}
+
+impl RoundRobinInput {
+    pub fn new() -> RoundRobinInput {
+        RoundRobinInput {
+            turn: 0
+        }
+    }
+}
+
+impl InputStrategy for RoundRobinInput {
+    fn select<'a, T, E>(&mut self, inputs: &mut [Inlet<'a, T, E>], stamp: u64) -> Option<usize> {
+        let n = inputs.len();
+        for k in range(0, n) {
+            let i = (self.turn + k) % n;
+            inputs[i].poll(stamp);
+            if inputs[i].is_ready() {
+                self.turn = i + 1;
+                return Some(i)
+            }
+        }
+        None
+    }
+}
+
+/// Priority
+/// Inputs are ranked by the order they were added in. An input is only
+/// taken from when every input ranked above it has nothing to give,
+/// so the first input is always drained before the second, and so on
+#[derive(Copy, Clone)]
+pub struct Priority;
+
+impl InputStrategy for Priority {
+    fn select<'a, T, E>(&mut self, inputs: &mut [Inlet<'a, T, E>], stamp: u64) -> Option<usize> {
+        for i in range(0, inputs.len()) {
+            inputs[i].poll(stamp);
+            if inputs[i].is_ready() {
+                return Some(i)
+            }
+        }
+        None
+    }
+}
+
+/// FirstAvailable
+/// In the manner of select, every input is polled, and the item
+/// which has been waiting the longest is taken first
+#[derive(Copy, Clone)]
+pub struct FirstAvailable;
+
+impl InputStrategy for FirstAvailable {
+    fn select<'a, T, E>(&mut self, inputs: &mut [Inlet<'a, T, E>], stamp: u64) -> Option<usize> {
+        let mut first: Option<usize> = None;
+        for i in range(0, inputs.len()) {
+            inputs[i].poll(stamp);
+            if inputs[i].is_ready() && first.map_or(true, |f| inputs[i].arrived() < inputs[f].arrived()) {
+                first = Some(i);
+            }
+        }
+        first
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::rc::Rc;
+    use std::cell::RefCell;
+    use std::sync::mpsc::channel;
+    use strategy::{Eager, RoundRobin, PerSubscriberQueue, RoundRobinInput, Priority, FirstAvailable};
+    use processor::{Broadcast, Take};
+    use publisher::{IterPublisher, Coupler, Select};
+    use subscriber::Collect;
+    use reactive::{Publisher, Subscriber, Subscription, UNBOUNDED};
+
+    // Collects what it is sent, but asks for nothing itself,
+    // the test asks on its behalf through ask
+    struct Puller<'a> {
+        v: &'a mut Box<Vec<u32>>,
+        sub: Rc<RefCell<Option<Subscription>>>
+    }
+
+    impl<'a> Subscriber for Puller<'a> {
+        type Input = u32;
+        type Error = ();
+
+        fn on_subscribe(&mut self, sub: Subscription) {
+            *self.sub.borrow_mut() = Some(sub);
+        }
+
+        fn on_next(&mut self, t: u32) -> bool {
+            self.v.push(t);
+            true
+        }
+    }
+
+    fn puller<'a>(v: &'a mut Box<Vec<u32>>) -> (Box<Puller<'a>>, Rc<RefCell<Option<Subscription>>>) {
+        let sub = Rc::new(RefCell::new(None));
+        (Box::new(Puller { v: v, sub: sub.clone() }), sub)
+    }
+
+    fn ask(sub: &Rc<RefCell<Option<Subscription>>>, n: usize) {
+        sub.borrow().as_ref().unwrap().request(n);
+    }
+
+    #[test]
+    fn broadcasts_to_everyone() {
+        let mut first = Box::new(Vec::new());
+        let mut second = Box::new(Vec::new());
+        {
+            let mut b = Broadcast::<u32, ()>::new();
+            b.subscribe(Box::new(Collect::new(&mut first)));
+            b.subscribe(Box::new(Collect::new(&mut second)));
+            let mut p = IterPublisher::new(range(0u32, 3));
+            p.subscribe(Box::new(b));
+            p.run();
+        }
+        assert_eq!(*first, vec![0, 1, 2]);
+        assert_eq!(*second, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn broadcast_drops_a_subscriber_which_stops() {
+        let mut all = Box::new(Vec::new());
+        let mut one = Box::new(Vec::new());
+        {
+            let mut b = Broadcast::<u32, ()>::new();
+            b.subscribe(Box::new(Collect::new(&mut all)));
+            let mut take = Take::new(1);
+            take.subscribe(Box::new(Collect::new(&mut one)));
+            b.subscribe(Box::new(take));
+            b.on_subscribe(Subscription::new(0));
+            for i in range(0u32, 3) {
+                assert!(b.on_next(i));
+            }
+            assert_eq!(b.len(), 1);
+            b.on_complete(false);
+        }
+        assert_eq!(*all, vec![0, 1, 2]);
+        assert_eq!(*one, vec![0]);
+    }
+
+    #[test]
+    fn broadcast_stops_once_everyone_has_gone() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut b = Broadcast::<u32, ()>::new();
+            let mut take = Take::new(2);
+            take.subscribe(Box::new(Collect::new(&mut v)));
+            b.subscribe(Box::new(take));
+            let mut p = IterPublisher::new(range(0u32, 1000));
+            p.subscribe(Box::new(b));
+            p.run();
+        }
+        assert_eq!(*v, vec![0, 1]);
+    }
+
+    #[test]
+    fn late_subscriber_gets_backlog_then_the_end() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut b = Broadcast::<u32, ()>::new();
+            b.on_subscribe(Subscription::new(0));
+            b.on_next(1);
+            b.on_complete(false);
+            b.subscribe_with(Box::new(Collect::new(&mut v)), vec![7, 8].into_iter());
+            assert_eq!(b.len(), 0);
+        }
+        assert_eq!(*v, vec![7, 8]);
+    }
+
+    #[test]
+    fn late_subscriber_gets_backlog_as_it_asks() {
+        let mut v = Box::new(Vec::new());
+        {
+            let (p, handle) = puller(&mut v);
+            let mut b = Broadcast::<u32, ()>::new();
+            b.on_subscribe(Subscription::new(0));
+            b.on_complete(false);
+            b.subscribe_with(p, vec![7, 8, 9].into_iter());
+            // nothing has been asked for yet, so nothing is sent
+            assert!(b.try_next());
+            ask(&handle, 2);
+            assert!(b.try_next());
+            // the end only goes out once all of the backlog has
+            ask(&handle, 1);
+            assert!(!b.try_next());
+        }
+        assert_eq!(*v, vec![7, 8, 9]);
+    }
+
+    #[test]
+    fn backlog_goes_out_ahead_of_new_items() {
+        let mut v = Box::new(Vec::new());
+        {
+            let (p, handle) = puller(&mut v);
+            let mut b = Broadcast::<u32, ()>::new();
+            b.subscribe_with(p, vec![7, 8].into_iter());
+            let up = Subscription::new(0);
+            b.on_subscribe(up.clone());
+            assert_eq!(up.demand(), 0);
+            // the backlog uses up two of the three asked for
+            ask(&handle, 3);
+            assert!(b.try_next());
+            assert_eq!(up.demand(), 1);
+            up.take();
+            b.on_next(1);
+        }
+        assert_eq!(*v, vec![7, 8, 1]);
+    }
+
+    #[test]
+    fn lock_step_waits_for_a_slow_subscriber() {
+        let mut fast = Box::new(Vec::new());
+        let mut slow = Box::new(Vec::new());
+        {
+            let (p, handle) = puller(&mut slow);
+            let mut b = Broadcast::<u32, ()>::new();
+            b.subscribe(Box::new(Collect::new(&mut fast)));
+            b.subscribe(p);
+            ask(&handle, 2);
+            let up = Subscription::new(0);
+            b.on_subscribe(up.clone());
+            assert_eq!(up.demand(), 2);
+
+            let mut source = range(0u32, 10);
+            while up.take() {
+                b.on_next(source.next().unwrap());
+            }
+            ask(&handle, 1);
+            assert!(b.try_next());
+            assert_eq!(up.demand(), 1);
+        }
+        assert_eq!(*fast, vec![0, 1]);
+        assert_eq!(*slow, vec![0, 1]);
+    }
+
+    #[test]
+    fn eager_skips_a_subscriber_without_demand() {
+        let mut fast = Box::new(Vec::new());
+        let mut slow = Box::new(Vec::new());
+        {
+            let (p, handle) = puller(&mut slow);
+            let mut b = Broadcast::<u32, (), _>::with_strategy(Eager);
+            b.subscribe(Box::new(Collect::new(&mut fast)));
+            b.subscribe(p);
+            ask(&handle, 1);
+            let up = Subscription::new(0);
+            b.on_subscribe(up.clone());
+            assert_eq!(up.demand(), UNBOUNDED);
+            b.on_next(0);
+            b.on_next(1);
+            ask(&handle, 1);
+            b.on_next(2);
+            b.on_complete(false);
+        }
+        assert_eq!(*fast, vec![0, 1, 2]);
+        assert_eq!(*slow, vec![0, 2]);
+    }
+
+    #[test]
+    fn round_robin_shares_out_items() {
+        let mut first = Box::new(Vec::new());
+        let mut second = Box::new(Vec::new());
+        {
+            let (p1, h1) = puller(&mut first);
+            let (p2, h2) = puller(&mut second);
+            let mut b = Broadcast::<u32, (), _>::with_strategy(RoundRobin::new());
+            b.subscribe(p1);
+            b.subscribe(p2);
+            ask(&h1, 2);
+            ask(&h2, 1);
+            let up = Subscription::new(0);
+            b.on_subscribe(up.clone());
+            assert_eq!(up.demand(), 3);
+
+            let mut source = range(0u32, 10);
+            while up.take() {
+                b.on_next(source.next().unwrap());
+            }
+            ask(&h2, 2);
+            assert!(b.try_next());
+            assert_eq!(up.demand(), 2);
+            while up.take() {
+                b.on_next(source.next().unwrap());
+            }
+        }
+        assert_eq!(*first, vec![0, 2]);
+        assert_eq!(*second, vec![1, 3, 4]);
+    }
+
+    #[test]
+    fn per_subscriber_queue_holds_up_only_the_slow() {
+        let mut fast = Box::new(Vec::new());
+        let mut slow = Box::new(Vec::new());
+        {
+            let (p, handle) = puller(&mut slow);
+            let mut b = Broadcast::<u32, (), _>::with_strategy(PerSubscriberQueue);
+            b.subscribe(Box::new(Collect::new(&mut fast)));
+            b.subscribe(p);
+            ask(&handle, 1);
+            let up = Subscription::new(0);
+            b.on_subscribe(up.clone());
+            assert_eq!(up.demand(), UNBOUNDED);
+            for i in range(0u32, 3) {
+                b.on_next(i);
+            }
+            ask(&handle, 5);
+            assert!(b.try_next());
+            b.on_complete(false);
+        }
+        assert_eq!(*fast, vec![0, 1, 2]);
+        assert_eq!(*slow, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn lock_step_leftovers_wait_for_demand_at_completion() {
+        let mut fast = Box::new(Vec::new());
+        let mut slow = Box::new(Vec::new());
+        {
+            let (p, handle) = puller(&mut slow);
+            let mut b = Broadcast::<u32, ()>::new();
+            b.subscribe(Box::new(Collect::new(&mut fast)));
+            b.subscribe(p);
+            b.on_subscribe(Subscription::new(0));
+            b.on_next(0);
+            b.on_next(1);
+            b.on_complete(false);
+            // the fast subscriber has had all of it and is done
+            assert_eq!(b.len(), 1);
+            ask(&handle, 1);
+            assert!(b.try_next());
+            assert_eq!(*slow, vec![0]);
+            ask(&handle, 1);
+            assert!(!b.try_next());
+        }
+        assert_eq!(*fast, vec![0, 1]);
+        assert_eq!(*slow, vec![0, 1]);
+    }
+
+    #[test]
+    fn round_robin_leftovers_wait_for_demand_at_completion() {
+        let mut first = Box::new(Vec::new());
+        let mut second = Box::new(Vec::new());
+        {
+            let (p1, h1) = puller(&mut first);
+            let (p2, h2) = puller(&mut second);
+            let mut b = Broadcast::<u32, (), _>::with_strategy(RoundRobin::new());
+            b.subscribe(p1);
+            b.subscribe(p2);
+            b.on_subscribe(Subscription::new(0));
+            for i in range(0u32, 3) {
+                b.on_next(i);
+            }
+            b.on_complete(false);
+            assert!(b.try_next());
+            assert!(first.is_empty() && second.is_empty());
+            ask(&h1, 2);
+            assert!(b.try_next());
+            ask(&h2, 1);
+            assert!(!b.try_next());
+        }
+        assert_eq!(*first, vec![0, 2]);
+        assert_eq!(*second, vec![1]);
+    }
+
+    #[test]
+    fn per_subscriber_queue_completes_once_the_queue_is_sent() {
+        let mut v = Box::new(Vec::new());
+        {
+            let (p, handle) = puller(&mut v);
+            let mut b = Broadcast::<u32, (), _>::with_strategy(PerSubscriberQueue);
+            b.subscribe(p);
+            b.on_subscribe(Subscription::new(0));
+            b.on_next(0);
+            b.on_next(1);
+            b.on_complete(false);
+            assert!(b.try_next());
+            assert!(v.is_empty());
+            ask(&handle, 2);
+            assert!(!b.try_next());
+        }
+        assert_eq!(*v, vec![0, 1]);
+    }
+
+    #[test]
+    fn round_robin_input_takes_turns() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut s = Select::<u32, (), _>::new(RoundRobinInput::new());
+            s.add(IterPublisher::new(range(0u32, 2)));
+            s.add(IterPublisher::new(range(10u32, 12)));
+            s.add(IterPublisher::new(range(20u32, 22)));
+            s.subscribe(Box::new(Collect::new(&mut v)));
+            s.run();
+        }
+        assert_eq!(*v, vec![0, 10, 20, 1, 11, 21]);
+    }
+
+    #[test]
+    fn priority_drains_the_first_input_first() {
+        let mut all = Box::new(Vec::new());
+        let mut limited = Box::new(Vec::new());
+        {
+            let mut s = Select::<u32, (), _>::new(Priority);
+            s.add(IterPublisher::new(range(0u32, 3)));
+            s.add(IterPublisher::new(range(10u32, 13)));
+            s.subscribe(Box::new(Collect::new(&mut all)));
+            s.run();
+
+            let mut s = Select::<u32, (), _>::new(Priority);
+            s.add(IterPublisher::new(range(0u32, 3)));
+            s.add(IterPublisher::new(range(10u32, 13)));
+            s.subscribe(Box::new(Collect::with_demand(&mut limited, 2)));
+            for _ in range(0, 20) { s.try_next(); }
+        }
+        assert_eq!(*all, vec![0, 1, 2, 10, 11, 12]);
+        assert_eq!(*limited, vec![0, 1]);
+    }
+
+    #[test]
+    fn priority_lets_a_busy_first_input_go_ahead() {
+        let (tx_a, rx_a) = channel();
+        let (tx_b, rx_b) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut s = Select::<u32, (), _>::new(Priority);
+            s.add(Coupler::new(rx_a));
+            s.add(Coupler::new(rx_b));
+            s.subscribe(Box::new(Collect::new(&mut v)));
+            tx_a.send(1).unwrap();
+            tx_b.send(10).unwrap();
+            s.try_next();
+            tx_a.send(2).unwrap();
+            s.try_next();
+            s.try_next();
+            drop(tx_a);
+            drop(tx_b);
+            while s.try_next() {}
+        }
+        assert_eq!(*v, vec![1, 2, 10]);
+    }
+
+    #[test]
+    fn first_available_takes_the_longest_waiting() {
+        let (tx_a, rx_a) = channel();
+        let (tx_b, rx_b) = channel();
+        let mut v = Box::new(Vec::new());
+        {
+            let mut s = Select::<u32, (), _>::new(FirstAvailable);
+            s.add(Coupler::new(rx_a));
+            s.add(Coupler::new(rx_b));
+            s.subscribe(Box::new(Collect::new(&mut v)));
+            tx_a.send(1).unwrap();
+            tx_b.send(10).unwrap();
+            s.try_next();
+            tx_a.send(2).unwrap();
+            s.try_next();
+            s.try_next();
+            drop(tx_a);
+            drop(tx_b);
+            while s.try_next() {}
+        }
+        assert_eq!(*v, vec![1, 10, 2]);
+    }
+
+    #[test]
+    fn nothing_is_held_for_nobody() {
+        let mut lock_step = Box::new(Vec::new());
+        let mut round_robin = Box::new(Vec::new());
+        {
+            let mut b = Broadcast::<u32, ()>::new();
+            b.on_next(1);
+            b.subscribe(Box::new(Collect::new(&mut lock_step)));
+            b.on_next(2);
+
+            let mut b = Broadcast::<u32, (), _>::with_strategy(RoundRobin::new());
+            b.on_next(1);
+            b.subscribe(Box::new(Collect::new(&mut round_robin)));
+            b.on_next(2);
+        }
+        assert_eq!(*lock_step, vec![2]);
+        assert_eq!(*round_robin, vec![2]);
+    }
+}
@@ -0,0 +1,494 @@
+// Copyright (C) 2015 <Rick Richardson r@12sidedtech.com>
+//
+// This software may be modified and distributed under the terms
+// of the MIT license.  See the LICENSE file for details.
+
+//! Subjects are both a way in and a way out. Application code pushes
+//! values in by calling next, error and complete (or the Subscriber methods
+//! they stand for), and any number of subscribers receive them, as with Broadcast.
+//! They take the place of a channel() and Coupler pair made just to inject values
+//!
+//! ```ignore
+//! let mut subj = PublishSubject::<u32, ()>::new();
+//! subj.subscribe(Box::new(StdoutSubscriber::new()));
+//! subj.next(5);
+//! subj.complete();
+//! ```
+
+use std::collections::RingBuf;
+use std::fmt::Debug;
+use reactive::{Publisher, Subscriber, Subscription};
+use processorimpl::Broadcast;
+
+/// PublishSubject
+/// Passes each value on to whoever is subscribed at the time
+pub struct PublishSubject<'a, I, E> where I : Clone, E : Clone {
+    inner: Broadcast<'a, I, E>
+}
+
+impl<'a, I, E> PublishSubject<'a, I, E> where I : Clone, E : Clone + Debug {
+    pub fn new() -> PublishSubject<'a, I, E> {
+        PublishSubject {
+            inner: Broadcast::new()
+        }
+    }
+
+    /// The number of subscribers still attached
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Sends a value to every subscriber.
+    /// Note this hides Publisher::next, which a subject has no use for
+    pub fn next(&mut self, t: I) -> bool {
+        self.on_next(t)
+    }
+
+    pub fn error(&mut self, err: E) {
+        self.on_error(err)
+    }
+
+    pub fn complete(&mut self) {
+        self.on_complete(false)
+    }
+}
+
+impl<'a, I, E> Publisher<'a> for PublishSubject<'a, I, E> where I : Clone, E : Clone {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        self.inner.subscribe(s);
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.inner.try_next()
+    }
+}
+
+impl<'a, I, E> Subscriber for PublishSubject<'a, I, E> where I : Clone, E : Clone + Debug {
+    type Input = I;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        self.inner.on_subscribe(sub);
+    }
+
+    fn on_next(&mut self, t: I) -> bool {
+        self.inner.on_next(t)
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.inner.on_error(err);
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        self.inner.on_complete(force);
+    }
+}
+
+/// BehaviorSubject
+/// Remembers the latest value, and sends it to each new subscriber
+/// before anything else, once the subscriber asks for it.
+/// Created either with a starting value, or empty
+pub struct BehaviorSubject<'a, I, E> where I : Clone, E : Clone {
+    inner: Broadcast<'a, I, E>,
+    latest: Option<I>
+}
+
+impl<'a, I, E> BehaviorSubject<'a, I, E> where I : Clone, E : Clone + Debug {
+    pub fn new(initial: I) -> BehaviorSubject<'a, I, E> {
+        BehaviorSubject {
+            inner: Broadcast::new(),
+            latest: Some(initial)
+        }
+    }
+
+    /// A BehaviorSubject with nothing to send new subscribers until its first value
+    pub fn empty() -> BehaviorSubject<'a, I, E> {
+        BehaviorSubject {
+            inner: Broadcast::new(),
+            latest: None
+        }
+    }
+
+    pub fn latest(&self) -> Option<&I> {
+        self.latest.as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Sends a value to every subscriber.
+    /// Note this hides Publisher::next, which a subject has no use for
+    pub fn next(&mut self, t: I) -> bool {
+        self.on_next(t)
+    }
+
+    pub fn error(&mut self, err: E) {
+        self.on_error(err)
+    }
+
+    pub fn complete(&mut self) {
+        self.on_complete(false)
+    }
+}
+
+impl<'a, I, E> Publisher<'a> for BehaviorSubject<'a, I, E> where I : Clone, E : Clone {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let latest = self.latest.clone();
+        self.inner.subscribe_with(s, latest.into_iter());
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.inner.try_next()
+    }
+}
+
+impl<'a, I, E> Subscriber for BehaviorSubject<'a, I, E> where I : Clone, E : Clone + Debug {
+    type Input = I;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        self.inner.on_subscribe(sub);
+    }
+
+    fn on_next(&mut self, t: I) -> bool {
+        self.latest = Some(t.clone());
+        self.inner.on_next(t)
+    }
+
+    fn on_error(&mut self, err: E) {
+        // once it has failed, there is no latest value to speak of
+        self.latest = None;
+        self.inner.on_error(err);
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        self.latest = None;
+        self.inner.on_complete(force);
+    }
+}
+
+/// ReplaySubject
+/// Remembers up to capacity of the most recent values, and sends
+/// them all to each new subscriber before anything else, as it asks
+/// for them. Subscribers arriving after the end still get the history
+pub struct ReplaySubject<'a, I, E> where I : Clone, E : Clone {
+    inner: Broadcast<'a, I, E>,
+    history: RingBuf<I>,
+    capacity: usize
+}
+
+impl<'a, I, E> ReplaySubject<'a, I, E> where I : Clone, E : Clone + Debug {
+    pub fn new(capacity: usize) -> ReplaySubject<'a, I, E> {
+        ReplaySubject {
+            inner: Broadcast::new(),
+            history: RingBuf::with_capacity(capacity),
+            capacity: capacity
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Sends a value to every subscriber.
+    /// Note this hides Publisher::next, which a subject has no use for
+    pub fn next(&mut self, t: I) -> bool {
+        self.on_next(t)
+    }
+
+    pub fn error(&mut self, err: E) {
+        self.on_error(err)
+    }
+
+    pub fn complete(&mut self) {
+        self.on_complete(false)
+    }
+}
+
+impl<'a, I, E> Publisher<'a> for ReplaySubject<'a, I, E> where I : Clone, E : Clone {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let history: Vec<I> = self.history.iter().map(|t| t.clone()).collect();
+        self.inner.subscribe_with(s, history.into_iter());
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.inner.try_next()
+    }
+}
+
+impl<'a, I, E> Subscriber for ReplaySubject<'a, I, E> where I : Clone, E : Clone + Debug {
+    type Input = I;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        self.inner.on_subscribe(sub);
+    }
+
+    fn on_next(&mut self, t: I) -> bool {
+        if self.capacity > 0 {
+            if self.history.len() == self.capacity {
+                self.history.pop_front();
+            }
+            self.history.push_back(t.clone());
+        }
+        self.inner.on_next(t)
+    }
+
+    fn on_error(&mut self, err: E) {
+        self.inner.on_error(err);
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        self.inner.on_complete(force);
+    }
+}
+
+/// AsyncSubject
+/// Sends nothing until it completes, then sends only the last value
+/// it was given, followed by completion, to each subscriber once it
+/// asks for it. Subscribers arriving after the end get the same.
+/// If it errors, the last value is never sent, and nothing after is kept
+pub struct AsyncSubject<'a, I, E> where I : Clone, E : Clone {
+    inner: Broadcast<'a, I, E>,
+    last: Option<I>,
+    completed: bool,
+    finished: bool
+}
+
+impl<'a, I, E> AsyncSubject<'a, I, E> where I : Clone, E : Clone + Debug {
+    pub fn new() -> AsyncSubject<'a, I, E> {
+        AsyncSubject {
+            inner: Broadcast::new(),
+            last: None,
+            completed: false,
+            finished: false
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Holds on to a value, it is only sent if it is the last.
+    /// Note this hides Publisher::next, which a subject has no use for
+    pub fn next(&mut self, t: I) -> bool {
+        self.on_next(t)
+    }
+
+    pub fn error(&mut self, err: E) {
+        self.on_error(err)
+    }
+
+    pub fn complete(&mut self) {
+        self.on_complete(false)
+    }
+}
+
+impl<'a, I, E> Publisher<'a> for AsyncSubject<'a, I, E> where I : Clone, E : Clone {
+    type Output = I;
+    type Error = E;
+
+    fn subscribe(&mut self, s: Box<Subscriber<Input=I, Error=E> + 'a>) {
+        let last = if self.completed { self.last.clone() } else { None };
+        self.inner.subscribe_with(s, last.into_iter());
+    }
+
+    fn try_next(&mut self) -> bool {
+        self.inner.try_next()
+    }
+}
+
+impl<'a, I, E> Subscriber for AsyncSubject<'a, I, E> where I : Clone, E : Clone + Debug {
+    type Input = I;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        self.inner.on_subscribe(sub);
+    }
+
+    fn on_next(&mut self, t: I) -> bool {
+        if self.finished {
+            return false
+        }
+        self.last = Some(t);
+        true
+    }
+
+    fn on_error(&mut self, err: E) {
+        if self.finished {
+            return
+        }
+        self.finished = true;
+        self.last = None;
+        self.inner.on_error(err);
+    }
+
+    fn on_complete(&mut self, force: bool) {
+        if self.finished {
+            return
+        }
+        self.finished = true;
+        self.completed = true;
+        // LockStep sends it only to subscribers which have all asked for it,
+        // otherwise it is left in their queues, ahead of the completion
+        if let Some(t) = self.last.clone() {
+            self.inner.on_next(t);
+        }
+        self.inner.on_complete(force);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use subject::{PublishSubject, BehaviorSubject, ReplaySubject, AsyncSubject};
+    use subscriber::Collect;
+    use reactive::Publisher;
+
+    #[test]
+    fn behavior_sends_latest() {
+        let mut early = Box::new(Vec::new());
+        let mut late = Box::new(Vec::new());
+        {
+            let mut subj = BehaviorSubject::<u32, ()>::new(0);
+            subj.subscribe(Box::new(Collect::new(&mut early)));
+            subj.next(1);
+            subj.next(2);
+            subj.subscribe(Box::new(Collect::new(&mut late)));
+            subj.next(3);
+        }
+        assert_eq!(*early, vec![0, 1, 2, 3]);
+        assert_eq!(*late, vec![2, 3]);
+    }
+
+    #[test]
+    fn publish_sends_only_what_comes_after() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut subj = PublishSubject::<u32, ()>::new();
+            subj.next(1);
+            subj.subscribe(Box::new(Collect::new(&mut v)));
+            subj.next(2);
+        }
+        assert_eq!(*v, vec![2]);
+    }
+
+    #[test]
+    fn behavior_pushed_before_subscribing() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut subj = BehaviorSubject::<u32, ()>::new(0);
+            subj.next(1);
+            subj.subscribe(Box::new(Collect::new(&mut v)));
+            subj.next(2);
+        }
+        assert_eq!(*v, vec![1, 2]);
+    }
+
+    #[test]
+    fn replay_pushed_before_subscribing() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut subj = ReplaySubject::<u32, ()>::new(2);
+            subj.next(1);
+            subj.next(2);
+            subj.next(3);
+            subj.subscribe(Box::new(Collect::new(&mut v)));
+            subj.next(4);
+        }
+        assert_eq!(*v, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn replay_is_bounded() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut subj = ReplaySubject::<u32, ()>::new(2);
+            subj.next(1);
+            subj.next(2);
+            subj.next(3);
+            subj.complete();
+            subj.subscribe(Box::new(Collect::new(&mut v)));
+        }
+        assert_eq!(*v, vec![2, 3]);
+    }
+
+    #[test]
+    fn async_sends_only_last() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut subj = AsyncSubject::<u32, ()>::new();
+            subj.subscribe(Box::new(Collect::new(&mut v)));
+            subj.next(1);
+            subj.next(2);
+            subj.complete();
+        }
+        assert_eq!(*v, vec![2]);
+    }
+
+    #[test]
+    fn replay_sends_history_as_it_is_asked_for() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut subj = ReplaySubject::<u32, ()>::new(3);
+            subj.next(1);
+            subj.next(2);
+            subj.next(3);
+            subj.subscribe(Box::new(Collect::with_demand(&mut v, 2)));
+            assert!(subj.try_next());
+        }
+        assert_eq!(*v, vec![1, 2]);
+    }
+
+    #[test]
+    fn behavior_waits_for_demand() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut subj = BehaviorSubject::<u32, ()>::new(0);
+            subj.subscribe(Box::new(Collect::with_demand(&mut v, 0)));
+            subj.next(1);
+            assert!(subj.try_next());
+        }
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn async_waits_for_demand() {
+        let mut v = Box::new(Vec::new());
+        {
+            let mut subj = AsyncSubject::<u32, ()>::new();
+            subj.subscribe(Box::new(Collect::with_demand(&mut v, 0)));
+            subj.next(1);
+            subj.complete();
+            // still owed the last value, so not yet completed
+            assert_eq!(subj.len(), 1);
+        }
+        assert!(v.is_empty());
+    }
+
+    #[test]
+    fn async_takes_nothing_after_an_error() {
+        let mut early = Box::new(Vec::new());
+        let mut late = Box::new(Vec::new());
+        {
+            let mut subj = AsyncSubject::<u32, ()>::new();
+            subj.subscribe(Box::new(Collect::new(&mut early)));
+            subj.next(1);
+            subj.error(());
+            assert!(!subj.next(2));
+            subj.complete();
+            subj.subscribe(Box::new(Collect::new(&mut late)));
+        }
+        assert!(early.is_empty());
+        assert!(late.is_empty());
+    }
+}
@@ -3,25 +3,26 @@
 // This software may be modified and distributed under the terms
 // of the MIT license.  See the LICENSE file for details.
 
-use std::fmt::Display;
-use reactive::{Subscriber};
+use std::fmt::{Display, Debug};
+use reactive::{Subscriber, Subscription, UNBOUNDED};
 use sendable::Sendable;
 
-pub struct StdoutSubscriber<A> where A : Display {
+pub struct StdoutSubscriber<A, E> where A : Display {
     index: Option<usize>
 }
 
-impl<A> StdoutSubscriber<A> where A : Display {
+impl<A, E> StdoutSubscriber<A, E> where A : Display {
 
-    pub fn new() -> StdoutSubscriber<A> {
+    pub fn new() -> StdoutSubscriber<A, E> {
         StdoutSubscriber {
             index: None
         }
     }
 }
 
-impl<A> Subscriber for StdoutSubscriber<A> where A : Display {
+impl<A, E> Subscriber for StdoutSubscriber<A, E> where A : Display, E : Debug {
     type Input = A;
+    type Error = E;
 
     fn on_next(&mut self, t: A) -> bool {
         println!("{}", t);
@@ -29,54 +30,77 @@ impl<A> Subscriber for StdoutSubscriber<A> where A : Display {
     }
 }
 
-pub struct Decoupler<Q, I> where I : Send, Q : Sendable {
-    index: Option<usize>,
+pub struct Decoupler<Q, I, E> where I : Send, Q : Sendable {
+    subscription: Option<Subscription>,
     data_tx: Q,
 }
 
-impl<Q, I> Decoupler<Q, I> where I : Send, Q : Sendable {
+impl<Q, I, E> Decoupler<Q, I, E> where I : Send, Q : Sendable {
 
-    pub fn new(tx: Q) -> Decoupler<Q,I> {
+    pub fn new(tx: Q) -> Decoupler<Q, I, E> {
         Decoupler {
-            index: None,
+            subscription: None,
             data_tx: tx,
         }
     }
 }
 
-impl<Q, I> Subscriber for Decoupler<Q, I>
-where I : Send,
+impl<Q, I, E> Subscriber for Decoupler<Q, I, E>
+where I : Send, E : Debug,
       Q : Sendable<Item=I>
 {
     type Input = I;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        sub.request(UNBOUNDED);
+        self.subscription = Some(sub);
+    }
 
     fn on_next(&mut self, t: I) -> bool {
         //TODO better handle queue failure, maybe put the returned buf
         //isizeo a recovery queue
         match self.data_tx.send(t) {
             Ok(()) => true,
-            Err(_) => false
+            Err(_) => {
+                // the receiving end hung up, so there is no point in
+                // anything upstream of us carrying on
+                if let Some(sub) = self.subscription.as_ref() { sub.cancel(); }
+                false
+            }
         }
     }
 }
 
-pub struct Collect<'a, I> where I : 'a {
+pub struct Collect<'a, I, E> where I : 'a {
     index: Option<usize>,
-    val: &'a mut Box<Vec<I>>
+    val: &'a mut Box<Vec<I>>,
+    demand: usize
 }
 
-impl<'a, I> Collect<'a, I> where I : 'a {
+impl<'a, I, E> Collect<'a, I, E> where I : 'a {
 
-    pub fn new(v : &'a mut Box<Vec<I>>) -> Collect<'a, I> {
+    pub fn new(v : &'a mut Box<Vec<I>>) -> Collect<'a, I, E> {
+        Collect::with_demand(v, UNBOUNDED)
+    }
+
+    /// Asks for n items when subscribed, and never for any more
+    pub fn with_demand(v : &'a mut Box<Vec<I>>, n: usize) -> Collect<'a, I, E> {
         Collect {
             index: None,
-            val: v
+            val: v,
+            demand: n
         }
     }
 }
 
-impl<'a, I> Subscriber for Collect<'a, I> where I : 'a {
+impl<'a, I, E> Subscriber for Collect<'a, I, E> where I : 'a, E : Debug {
     type Input = I;
+    type Error = E;
+
+    fn on_subscribe(&mut self, sub: Subscription) {
+        sub.request(self.demand);
+    }
 
     fn on_next(&mut self, t: I) -> bool {
         self.val.push(t);
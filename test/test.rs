@@ -21,7 +21,8 @@ use alloc::heap as a;
 use std::sync::Arc;
 
 use rx::reactive::{Publisher, Subscriber};
-use rx::publisher::{IterPublisher, Coupler};
+use rx::publisher::{IterPublisher};
+use rx::net_stream::NetStreamer;
 use rx::subscriber::{StdoutSubscriber, Decoupler};
 use rx::processor::{MapVal1, Map, DebugWhile, Enumerate};
 use rx::sendable::{Sendable};
@@ -67,7 +68,7 @@ fn main() {
     let mut ne = NetEngine::<BufProtocol<SixtyFour>>::new();
     type Msg = ProtoMsg<AROIobuf>;
 
-    let srv_rx = ne.listen("127.0.0.1", 10000).unwrap();
+    let srv = ne.listen("127.0.0.1", 10000).unwrap();
     let cli = ne.connect("127.0.0.1", 10000).unwrap();
 
     let token = cli.tok.clone();
@@ -76,7 +77,7 @@ fn main() {
     ne.timeout(Duration::milliseconds(1000), Box::new(|&: el : &mut Reactor| { el.shutdown(); true}));
 
     let out = move |:| {
-        let mut rec = Box::new(Coupler::new(srv_rx));
+        let mut rec = Box::new(NetStreamer::new(srv));
         let mut map1 = Box::new(Map::new(| tup |  -> (Msg, u64) {  (tup, precise_time_ns()) }));
         let mut red = Box::new(Enumerate::new());
         let mut trace = Box::new(DebugWhile::new(| &(_,count) : &((Msg, u64), u64) | { count % 10000 == 0 }));